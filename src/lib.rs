@@ -1,20 +1,523 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, LookupSet};
+use near_sdk::collections::{LookupMap, LookupSet, TreeMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
 use near_sdk::{
-  env, near_bindgen, AccountId, PanicOnDefault, PublicKey,
+  env, ext_contract, is_promise_success, near_bindgen, AccountId, Balance, CurveType, FunctionError, Gas,
+  PanicOnDefault, Promise, PromiseOrValue, PublicKey,
 };
+use std::fmt;
+
+/// Contract specification version, bumped with every release.
+const SPEC_VERSION: &str = "0.1.0";
+/// Contract name reported in the metadata.
+const CONTRACT_NAME: &str = "smart_whitelist";
+
+/// Default standard name of the events emitted by the contract.
+const EVENT_STANDARD: &str = "smart_whitelist";
+/// Default version of the events emitted by the contract.
+const EVENT_VERSION: &str = "1.0.0";
+
+/// Maximum length of the internal note attached to a whitelisted account.
+const MAX_NOTE_LENGTH: usize = 256;
+
+/// Maximum length of the whitelist removal reason.
+const MAX_REASON_LENGTH: usize = 256;
+
+/// Maximum length of the applicant contact handle.
+const MAX_CONTACT_LENGTH: usize = 128;
+
+/// Length of the KYC documents hash.
+const DOC_HASH_LENGTH: usize = 32;
+
+/// Default maximum number of entries accepted by batch methods.
+const DEFAULT_MAX_BATCH_SIZE: u64 = 100;
+
+/// Default time after which a pending applicant is considered stale (30 days).
+const DEFAULT_APPLICANT_TTL_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Minimum delay between queueing and executing a time-locked admin action (24 hours).
+const ADMIN_ACTION_DELAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Time during which a removed account can be restored (30 days).
+const REMOVED_RETENTION_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Maximum number of public keys registered by a single applicant.
+const MAX_APPLICANT_KEYS: usize = 5;
+
+/// Maximum number of items returned by paginated getters.
+const MAX_PAGE_LIMIT: u64 = 100;
+
+/// Maximum number of service accounts returned by `get_all_service_accounts`.
+const MAX_ALL_SERVICE_ACCOUNTS: u64 = 500;
+
+/// Upper-bound estimate of the storage used by a new applicant in bytes.
+const APPLICANT_STORAGE_USAGE: u64 = 800;
+
+/// Maximum length of the service account label.
+const MAX_SERVICE_LABEL_LENGTH: usize = 64;
+
+/// Default time window of the per-service approval rate limit (1 hour).
+const DEFAULT_SERVICE_RATE_WINDOW_NS: u64 = 60 * 60 * 1_000_000_000;
+
+/// Maximum number of whitelist purposes.
+const MAX_PURPOSES: u64 = 16;
+
+/// Maximum length of the whitelist purpose.
+const MAX_PURPOSE_LENGTH: usize = 32;
+
+/// Whitelist purpose backed by the KYC whitelist and used by the single-purpose methods.
+const DEFAULT_PURPOSE: &str = "default";
+
+/// Gas to attach to the cross-contract `is_whitelisted` call, a conservative estimate.
+pub const WHITELIST_CHECK_GAS: Gas = Gas(5_000_000_000_000);
+/// Gas attached to the callback consuming the `is_whitelisted` result.
+const ON_WHITELIST_CHECKED_GAS: Gas = Gas(5_000_000_000_000);
+/// Gas attached to the notification of the consumer contract about a whitelisted account.
+const ON_ACCOUNT_WHITELISTED_GAS: Gas = Gas(5_000_000_000_000);
+/// Gas attached to the callback resolving the fees withdrawal transfer.
+const ON_FEES_WITHDRAWN_GAS: Gas = Gas(5_000_000_000_000);
+
+/// Interface of the whitelist contract for cross-contract calls.
+#[ext_contract(ext_whitelist)]
+pub trait ExtWhitelist {
+  /// Returns 'true' if the given account ID is whitelisted.
+  fn is_whitelisted(&self, account_id: AccountId) -> bool;
+}
+
+/// Callbacks of the contract.
+#[ext_contract(ext_self)]
+pub trait ExtSelf {
+  /// Consumes the result of the cross-contract `is_whitelisted` call.
+  fn on_whitelist_checked(&self) -> bool;
+  /// Consumes the result of the cross-contract `is_whitelisted` call to the fallback whitelist.
+  fn on_fallback_checked(&self) -> bool;
+  /// Restores the withdrawn fees if their transfer failed.
+  fn on_fees_withdrawn(&mut self, amount: U128) -> bool;
+}
+
+/// Interface of the consumer contract notified about whitelisted accounts.
+#[ext_contract(ext_whitelist_consumer)]
+pub trait ExtWhitelistConsumer {
+  /// Handles the account ID added to the whitelist.
+  fn on_account_whitelisted(&mut self, account_id: AccountId);
+}
+
+/// Creates the cross-contract `is_whitelisted` call to the whitelist contract with `WHITELIST_CHECK_GAS` attached.
+pub fn is_whitelisted_promise(whitelist_account_id: AccountId, account_id: AccountId) -> Promise {
+  ext_whitelist::is_whitelisted(account_id, whitelist_account_id, 0, WHITELIST_CHECK_GAS)
+}
+
+/// Sensitive admin action executed through the timelock.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AdminAction {
+  /// Removes the service account ID with all of its per-service data.
+  RemoveServiceAccount { service_account_id: AccountId },
+  /// Permanently renounces the administration, the confirmation must match the contract account ID.
+  RenounceAdmin { confirm: AccountId },
+  /// Replaces all service accounts with the given ones, removing the per-service data of the old ones.
+  /// Requires guardian confirmations of `get_replace_service_accounts_hash` when the guardian threshold is set.
+  ReplaceServiceAccounts { new_services: Vec<AccountId> },
+}
+
+/// Errors returned by the applicant registration and whitelisting methods.
+#[derive(BorshDeserialize, BorshSerialize, FunctionError, Debug, PartialEq)]
+pub enum ContractError {
+  RegistrationPaused,
+  AlreadyWhitelisted,
+  ReapplicationCooldown,
+  PublicKeyAlreadyRegistered,
+  ReferrerNotWhitelisted,
+  DepositRequired,
+  TooManyPublicKeys,
+  ApplicantQueueFull,
+  InsufficientDeposit,
+  WhitelistingPaused,
+  SelfWhitelisting,
+  UnknownApplicant,
+  ServiceQuotaExhausted,
+  UnsupportedKeyCurve,
+  PendingApproval,
+  ApplicantHasNoKey,
+  ContactTooLong,
+}
+
+impl fmt::Display for ContractError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let message = match self {
+      ContractError::RegistrationPaused => "Registration is paused",
+      ContractError::AlreadyWhitelisted => "Account ID is already whitelisted",
+      ContractError::ReapplicationCooldown => "Re-application is in cooldown",
+      ContractError::PublicKeyAlreadyRegistered => "Public key already registered",
+      ContractError::ReferrerNotWhitelisted => "Referrer is not whitelisted",
+      ContractError::DepositRequired => "Attach a deposit to cover storage; call get_applicant_storage_cost",
+      ContractError::TooManyPublicKeys => "Too many public keys",
+      ContractError::ApplicantQueueFull => "Applicant queue is full",
+      ContractError::InsufficientDeposit => "Attached deposit must cover the registration fee and storage",
+      ContractError::WhitelistingPaused => "Whitelisting is paused",
+      ContractError::SelfWhitelisting => "Service cannot whitelist itself",
+      ContractError::UnknownApplicant => "Unknown applicant",
+      ContractError::ServiceQuotaExhausted => "Service quota exhausted",
+      ContractError::UnsupportedKeyCurve => "Unsupported key curve",
+      ContractError::PendingApproval => "Account ID is pending admin approval",
+      ContractError::ApplicantHasNoKey => "Applicant has no valid key",
+      ContractError::ContactTooLong => "Contact is too long",
+    };
+    f.write_str(message)
+  }
+}
+
+/// Category of the whitelist removal reason.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RemovalReason {
+  Expired,
+  ComplianceViolation,
+  UserRequest,
+  Fraud,
+  /// Free-form reason not covered by the other categories.
+  Other(String),
+}
+
+/// Applicant pending KYC verification.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Applicant {
+  /// Public keys of the applicant.
+  pub pks: Vec<PublicKey>,
+  /// Block timestamp of the registration.
+  pub submitted_at: u64,
+  /// Deposit held for the applicant's storage.
+  pub storage_deposit: Balance,
+  /// Position of the applicant in the FIFO applicant queue.
+  pub queue_position: u64,
+  /// Hash of the KYC documents submitted by the applicant.
+  pub doc_hash: Option<Vec<u8>>,
+  /// Whitelisted account ID that referred the applicant.
+  pub referrer: Option<AccountId>,
+  /// Contact handle for rejection notifications, stored in plaintext and readable by anyone via `view_state`.
+  pub contact: Option<String>,
+}
+
+/// Whitelist record of the account that completed KYC verification.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct WhitelistRecord {
+  /// Internal reviewer note, exposed only via `get_account_note`.
+  pub note: Option<String>,
+  /// Service account ID that verified the account, 'None' if whitelisted by the administrator.
+  pub verified_by: Option<AccountId>,
+  /// Whitelisted account ID that referred the account.
+  pub referrer: Option<AccountId>,
+  /// Block height at which the account was whitelisted.
+  pub whitelisted_block: u64,
+  /// Block timestamp from which the account is whitelisted.
+  pub valid_from: u64,
+  /// Block timestamp at which the KYC verification expires, 'None' if it never expires.
+  pub expires_at: Option<u64>,
+  /// Public keys carried over from the applicant at the approval.
+  pub pks: Vec<PublicKey>,
+  /// Block timestamp at which the account was first whitelisted.
+  pub first_seen: u64,
+  /// Block timestamp of the last change of the record.
+  pub updated_at: u64,
+}
+
+impl WhitelistRecord {
+  /// Creates the whitelist record with the given internal note at the current block height.
+  pub fn new(note: Option<String>) -> Self {
+    Self {
+      note,
+      verified_by: None,
+      referrer: None,
+      whitelisted_block: env::block_index(),
+      valid_from: 0,
+      expires_at: None,
+      pks: Vec::new(),
+      first_seen: env::block_timestamp(),
+      updated_at: env::block_timestamp(),
+    }
+  }
+
+  /// Returns 'true' if the given timestamp is within the validity window of the record.
+  pub fn is_valid_at(&self, timestamp: u64) -> bool {
+    self.valid_from <= timestamp && self.expires_at.map_or(true, |expires_at| timestamp < expires_at)
+  }
+}
+
+/// Whitelist record of the removed account kept for the retention window.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RemovedAccount {
+  /// Whitelist record of the account at the time of removal.
+  pub record: WhitelistRecord,
+  /// Block timestamp of the removal.
+  pub removed_at: u64,
+}
+
+/// Whitelist entry of the account returned by `get_whitelist_entry`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WhitelistEntry {
+  /// Whitelisted account ID.
+  pub account_id: AccountId,
+  /// Service account ID that verified the account.
+  pub verified_by: Option<AccountId>,
+  /// Whitelisted account ID that referred the account.
+  pub referrer: Option<AccountId>,
+  /// Block height at which the account was whitelisted.
+  pub whitelisted_block: u64,
+  /// Block timestamp from which the account is whitelisted.
+  pub valid_from: u64,
+  /// Block timestamp at which the KYC verification expires, 'None' if it never expires.
+  pub expires_at: Option<u64>,
+  /// Block timestamp at which the account was first whitelisted.
+  pub first_seen: u64,
+  /// Block timestamp of the last change of the record.
+  pub updated_at: u64,
+  /// Whether the account is frozen.
+  pub frozen: bool,
+  /// Whether the account is currently considered whitelisted.
+  pub is_active: bool,
+}
+
+/// Applicant details returned by `get_applicant`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApplicantView {
+  /// Applicant account ID.
+  pub account_id: AccountId,
+  /// Public keys of the applicant.
+  pub pks: Vec<PublicKey>,
+  /// Block timestamp of the registration.
+  pub submitted_at: u64,
+  /// Whitelisted account ID that referred the applicant.
+  pub referrer: Option<AccountId>,
+  /// Contact handle for rejection notifications.
+  pub contact: Option<String>,
+}
+
+/// KYC status of the account.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AccountStatus {
+  /// Neither an applicant nor whitelisted.
+  Unknown,
+  /// Registered and pending KYC verification.
+  Applicant,
+  /// Whitelisted.
+  Whitelisted,
+  /// Whitelisted but temporarily suspended.
+  Frozen,
+  /// Whitelisted but the KYC verification has expired.
+  Expired,
+  /// Whitelisted with the validity window starting in the future.
+  Scheduled,
+}
+
+/// Contract metadata used by deployment tooling.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractMetadata {
+  /// Contract specification version.
+  pub spec_version: String,
+  /// Contract name.
+  pub name: String,
+  /// Number of service accounts.
+  pub service_accounts_count: u64,
+  /// Number of applicants.
+  pub applicants_count: u64,
+  /// Number of whitelisted accounts.
+  pub whitelisted_count: u64,
+}
+
+/// Live storage usage of the contract account.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageReport {
+  /// Storage used by the contract account in bytes.
+  pub storage_usage: u64,
+  /// Balance of the contract account.
+  pub storage_balance: U128,
+}
+
+/// Service account with all of its per-service fields returned by `get_service_account`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ServiceAccount {
+  /// Service account ID.
+  pub account_id: AccountId,
+  /// Human-readable label.
+  pub label: String,
+  /// Lifetime approval quota, unlimited if not set.
+  pub quota: Option<u64>,
+  /// Number of accounts whitelisted by the service account.
+  pub approval_count: u64,
+  /// Number of approvals left in the quota, unlimited if not set.
+  pub remaining_quota: Option<u64>,
+  /// Number of approvals left in the current rate limit window, unlimited if not set.
+  pub remaining_rate_limit: Option<u64>,
+  /// Expiration timestamp of the temporary grant, 'None' if permanent.
+  pub expires_at: Option<u64>,
+  /// Whether the service account is suspended by the administrator.
+  pub suspended: bool,
+}
+
+/// Aggregate contract statistics used by monitoring.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractStats {
+  /// Number of whitelisted accounts.
+  pub whitelisted_count: u64,
+  /// Number of applicants.
+  pub applicant_count: u64,
+  /// Number of service accounts.
+  pub service_account_count: u64,
+  /// Storage used by the contract account in bytes.
+  pub storage_usage: u64,
+}
+
+/// Consistency checks of the contract state used by monitoring, every field is 'true' for a healthy contract.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantReport {
+  /// Every applicant has exactly one applicant queue entry at its queue position.
+  pub applicants_map_matches_order_vector: bool,
+  /// Every public key of an applicant is indexed to that applicant.
+  pub applicant_pks_indexed: bool,
+  /// No applicant account ID is whitelisted at the same time.
+  pub no_account_both_whitelisted_and_applicant: bool,
+  /// No account ID pending the administrator approval is whitelisted at the same time.
+  pub no_account_both_whitelisted_and_pending: bool,
+  /// The whitelist does not exceed its maximum size.
+  pub whitelist_within_capacity: bool,
+}
+
+/// State layout of the first contract release read by `migrate`.
+/// Its collection keys are the prefix followed by the account ID length below 64, so they never collide
+/// with the sub-prefixed keys of the current collections sharing the same prefixes.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacySmartWhitelistContract {
+  pub admin_pk: PublicKey,
+  pub service_accounts: LookupSet<AccountId>,
+  pub applicants: LookupMap<AccountId, PublicKey>,
+  pub whitelist: LookupSet<AccountId>,
+}
+
+impl LegacySmartWhitelistContract {
+  /// Returns the legacy state with the collection prefixes used by the first contract release.
+  fn new(admin_pk: PublicKey) -> Self {
+    Self {
+      admin_pk,
+      service_accounts: LookupSet::new(b"s".to_vec()),
+      applicants: LookupMap::new(b"a"),
+      whitelist: LookupSet::new(b"w".to_vec()),
+    }
+  }
+}
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct SmartWhitelistContract {
   /// Whitelist administrator public key.
   pub admin_pk: PublicKey,
+  /// Block timestamp of the contract initialization.
+  pub created_at: u64,
+  /// Consumer contract notified about accounts whitelisted by service accounts.
+  pub notify_contract: Option<AccountId>,
+  /// Whitelist contract checked for account IDs that are not whitelisted locally.
+  pub fallback_whitelist: Option<AccountId>,
+  /// Standard name of the emitted events.
+  pub event_standard: String,
+  /// Standard version of the emitted events.
+  pub event_version: String,
   /// Service accounts.
-  pub service_accounts: LookupSet<AccountId>,
-  /// Applicant public keys for whitelisting.
-  pub applicants: LookupMap<AccountId, PublicKey>,
-  /// Whitelisted account IDs that completed KYC verification.
-  pub whitelist: LookupSet<AccountId>,
+  pub service_accounts: UnorderedSet<AccountId>,
+  /// Human-readable labels of the service accounts.
+  pub service_labels: LookupMap<AccountId, String>,
+  /// Number of accounts whitelisted by each service account.
+  pub approval_counts: LookupMap<AccountId, u64>,
+  /// Applicants for whitelisting.
+  pub applicants: UnorderedMap<AccountId, Applicant>,
+  /// Applicant account IDs by their public keys.
+  pub pk_index: LookupMap<PublicKey, AccountId>,
+  /// Whitelisted account IDs by the public keys carried over from their applications.
+  pub whitelisted_pk_index: LookupMap<PublicKey, AccountId>,
+  /// Whitelist records of the account IDs that completed KYC verification.
+  pub whitelist: UnorderedMap<AccountId, WhitelistRecord>,
+  /// Whitelisted account IDs temporarily suspended pending re-review.
+  pub frozen: LookupSet<AccountId>,
+  /// Whitelisted account IDs flagged for re-verification after a KYC policy change.
+  pub needs_reverification: LookupSet<AccountId>,
+  /// Maximum number of whitelisted accounts, unlimited if not set.
+  pub max_whitelist_size: Option<u64>,
+  /// Whether the administration has been permanently renounced.
+  pub admin_renounced: bool,
+  /// Time after which a pending applicant can be expired.
+  pub applicant_ttl_ns: u64,
+  /// Time during which the KYC verification of a whitelisted account is valid, unlimited if zero.
+  pub kyc_validity_ns: u64,
+  /// Flat fee charged on the applicant registration.
+  pub registration_fee: Balance,
+  /// Registration fees collected by the contract, excluding storage deposits.
+  pub collected_fees: Balance,
+  /// Time-locked admin action with the timestamp after which it can be executed.
+  pub pending_admin_action: Option<(AdminAction, u64)>,
+  /// Timestamps until which rejected applicants cannot register again.
+  pub rejected_until: LookupMap<AccountId, u64>,
+  /// Time during which a rejected applicant cannot register again.
+  pub reject_cooldown_ns: u64,
+  /// Guardian public keys confirming sensitive admin actions.
+  pub guardians: UnorderedSet<PublicKey>,
+  /// Number of guardian confirmations required for a sensitive admin action, disabled if zero.
+  pub guardian_threshold: u8,
+  /// Guardian public keys that confirmed each action hash.
+  pub confirmations: LookupMap<Vec<u8>, Vec<PublicKey>>,
+  /// Whether new applicant registrations are paused.
+  pub registration_paused: bool,
+  /// Whether whitelisting by service accounts is paused.
+  pub whitelisting_paused: bool,
+  /// Maximum number of concurrent applicants, unlimited if not set.
+  pub max_applicants: Option<u64>,
+  /// Applicant account IDs ordered by their registration.
+  pub applicant_queue: TreeMap<u64, AccountId>,
+  /// Queue position assigned to the next registered applicant.
+  pub next_queue_position: u64,
+  /// Whether implicit (64 hex characters) account IDs can be whitelisted.
+  pub allow_implicit_accounts: bool,
+  /// Removed accounts that can still be restored.
+  pub removed: LookupMap<AccountId, RemovedAccount>,
+  /// Lifetime approval quotas of the service accounts, unlimited if not set.
+  pub service_quotas: LookupMap<AccountId, u64>,
+  /// Expiration timestamps of the temporary service account grants.
+  pub service_expirations: LookupMap<AccountId, u64>,
+  /// Maximum number of accounts a single service account can whitelist per window, unlimited if not set.
+  pub service_rate_limit: Option<u64>,
+  /// Time window of the per-service approval rate limit.
+  pub service_rate_window_ns: u64,
+  /// Start of the current rate limit window with the number of approvals in it for each service account.
+  pub service_rate_windows: LookupMap<AccountId, (u64, u64)>,
+  /// Service accounts temporarily suspended by the administrator, keeping their configuration.
+  pub suspended_services: LookupSet<AccountId>,
+  /// Registered whitelist purposes, e.g. separate products.
+  pub purposes: UnorderedSet<String>,
+  /// Whitelist records of the accounts of each registered purpose, independent of the default purpose.
+  pub purpose_whitelist: LookupMap<(String, AccountId), WhitelistRecord>,
+  /// Sequence number of the last emitted event.
+  pub event_seq: u64,
+  /// Curve type bytes of the public keys accepted at the applicant registration.
+  pub allowed_curves: Vec<u8>,
+  /// Whether accounts verified by service accounts need the final administrator approval.
+  pub require_admin_approval: bool,
+  /// Whitelist records of the verified accounts pending the administrator approval.
+  pub pending_whitelist: UnorderedMap<AccountId, WhitelistRecord>,
+  /// Maximum number of entries accepted by batch methods.
+  pub max_batch_size: u64,
+  /// Number of accounts ever added to the whitelist of any purpose, never decremented on removal.
+  pub total_approvals: u64,
+  /// Whether all state changes except leaving the read-only mode are forbidden.
+  pub read_only: bool,
+  /// Backup public key acting as the administrator until the given block timestamp.
+  pub delegated_admin: Option<(PublicKey, u64)>,
 }
 
 #[near_bindgen]
@@ -22,374 +525,5296 @@ impl SmartWhitelistContract {
   /// Initializes the contract with the specified public key of the whitelist administrator.
   #[init]
   pub fn new(admin_pk: PublicKey) -> Self {
-    Self {
-      admin_pk,
-      service_accounts: LookupSet::new(b"s".to_vec()),
-      applicants: LookupMap::new(b"a"),
-      whitelist: LookupSet::new(b"w".to_vec()),
+    Self::internal_new(admin_pk, "")
+  }
+
+  /// Initializes the contract with the storage prefixes of all collections namespaced by the given prefix.
+  /// Prevents storage collisions between instances composed under a factory.
+  #[init]
+  pub fn new_with_prefixes(admin_pk: PublicKey, prefix: String) -> Self {
+    Self::internal_new(admin_pk, &prefix)
+  }
+
+  /// Migrates the contract state after the code upgrade.
+  /// The current state is kept as is, including `created_at`. The state of the first release is rebuilt
+  /// with `created_at` set to the migration time and the given legacy service accounts carried over,
+  /// its whitelisted accounts and applicants are moved by `migrate_legacy_accounts`.
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate(service_account_ids: Vec<AccountId>) -> Self {
+    let state = env::storage_read(b"STATE").unwrap_or_else(|| env::panic_str("Contract is not initialized"));
+    if let Ok(contract) = Self::try_from_slice(&state) {
+      return contract;
     }
+    let mut legacy = LegacySmartWhitelistContract::try_from_slice(&state)
+      .unwrap_or_else(|_| env::panic_str("Cannot deserialize the contract state"));
+    let mut contract = Self::internal_new(legacy.admin_pk.clone(), "");
+    for service_account_id in service_account_ids.iter() {
+      if legacy.service_accounts.remove(service_account_id) {
+        contract.service_accounts.insert(service_account_id);
+      }
+    }
+    contract.log_event("contract_migrated", json!({ "created_at": contract.created_at }));
+    contract
   }
 
   /**
     Getters
   **/
 
+  /// Returns 'true' if the contract state has been initialized.
+  /// Safe to call before `new`, as it does not deserialize the contract state.
+  pub fn is_initialized() -> bool {
+    env::state_exists()
+  }
+
+  /// Returns the contract metadata.
+  pub fn get_metadata(&self) -> ContractMetadata {
+    ContractMetadata {
+      spec_version: SPEC_VERSION.to_string(),
+      name: CONTRACT_NAME.to_string(),
+      service_accounts_count: self.service_accounts.len(),
+      applicants_count: self.applicants.len(),
+      whitelisted_count: self.whitelist.len(),
+    }
+  }
+
+  /// Returns the block timestamp of the contract initialization.
+  pub fn get_created_at(&self) -> u64 {
+    self.created_at
+  }
+
+  /// Returns the consumer contract notified about whitelisted accounts.
+  pub fn get_notify_contract(&self) -> Option<AccountId> {
+    self.notify_contract.clone()
+  }
+
+  /// Returns the whitelist contract checked for account IDs that are not whitelisted locally.
+  pub fn get_fallback_whitelist(&self) -> Option<AccountId> {
+    self.fallback_whitelist.clone()
+  }
+
+  /// Returns the standard name of the emitted events.
+  pub fn get_event_standard(&self) -> String {
+    self.event_standard.clone()
+  }
+
+  /// Returns the standard version of the emitted events.
+  pub fn get_event_version(&self) -> String {
+    self.event_version.clone()
+  }
+
+  /// Returns the aggregate contract statistics in a single call.
+  pub fn get_stats(&self) -> ContractStats {
+    ContractStats {
+      whitelisted_count: self.whitelist.len(),
+      applicant_count: self.applicants.len(),
+      service_account_count: self.service_accounts.len(),
+      storage_usage: env::storage_usage(),
+    }
+  }
+
+  /// Returns the consistency checks of the contract state, scanning the applicants and pending accounts.
+  pub fn check_invariants(&self) -> InvariantReport {
+    let queue_matches_applicants = self.applicant_queue.iter().all(|(queue_position, account_id)| {
+      self
+        .applicants
+        .get(&account_id)
+        .map_or(false, |applicant| applicant.queue_position == queue_position)
+    });
+    let applicants_match_queue = self
+      .applicants
+      .iter()
+      .all(|(account_id, applicant)| self.applicant_queue.get(&applicant.queue_position) == Some(account_id));
+    InvariantReport {
+      applicants_map_matches_order_vector: queue_matches_applicants && applicants_match_queue,
+      applicant_pks_indexed: self.applicants.iter().all(|(account_id, applicant)| {
+        applicant.pks.iter().all(|pk| self.pk_index.get(pk) == Some(account_id.clone()))
+      }),
+      no_account_both_whitelisted_and_applicant: self
+        .applicants
+        .keys()
+        .all(|account_id| self.whitelist.get(&account_id).is_none()),
+      no_account_both_whitelisted_and_pending: self
+        .pending_whitelist
+        .keys()
+        .all(|account_id| self.whitelist.get(&account_id).is_none()),
+      whitelist_within_capacity: self
+        .max_whitelist_size
+        .map_or(true, |max_whitelist_size| self.whitelist.len() <= max_whitelist_size),
+    }
+  }
+
+  /// Returns the live storage usage of the contract account.
+  pub fn get_storage_report(&self) -> StorageReport {
+    StorageReport {
+      storage_usage: env::storage_usage(),
+      storage_balance: U128(env::account_balance()),
+    }
+  }
+
   /// Returns 'true' if the given identifier is a service identifier.
   pub fn is_service_account_whitelisted(&self, service_account_id: AccountId) -> bool {
     self.service_accounts.contains(&service_account_id)
   }
 
-  /// Returns the public key for the applicant's account
+  /// Returns the service account with all of its per-service fields.
+  pub fn get_service_account(&self, service_account_id: AccountId) -> Option<ServiceAccount> {
+    if !self.service_accounts.contains(&service_account_id) {
+      return None;
+    }
+    let (_, window_count) = self.internal_get_rate_window(&service_account_id);
+    Some(ServiceAccount {
+      label: self.service_labels.get(&service_account_id).unwrap_or_default(),
+      quota: self.service_quotas.get(&service_account_id),
+      approval_count: self.get_service_approval_count(service_account_id.clone()),
+      remaining_quota: self.get_service_remaining_quota(service_account_id.clone()),
+      remaining_rate_limit: self
+        .service_rate_limit
+        .map(|service_rate_limit| service_rate_limit.saturating_sub(window_count)),
+      expires_at: self.service_expirations.get(&service_account_id),
+      suspended: self.suspended_services.contains(&service_account_id),
+      account_id: service_account_id,
+    })
+  }
+
+  /// Returns the calling service account with its limits, 'None' if the caller is not a service account.
+  /// Transaction-only, including cross-contract calls: the predecessor account is unavailable in view calls,
+  /// so integrations should view `get_service_account` with their account ID instead.
+  pub fn get_my_service_info(&self) -> Option<ServiceAccount> {
+    self.get_service_account(env::predecessor_account_id())
+  }
+
+  /// Returns the label of the service account.
+  pub fn get_service_label(&self, service_account_id: AccountId) -> Option<String> {
+    self.service_labels.get(&service_account_id)
+  }
+
+  /// Returns the number of approvals left in the service account quota, 'None' if unlimited.
+  pub fn get_service_remaining_quota(&self, service_account_id: AccountId) -> Option<u64> {
+    let quota = self.service_quotas.get(&service_account_id)?;
+    Some(quota.saturating_sub(self.get_service_approval_count(service_account_id)))
+  }
+
+  /// Returns 'true' if the service account is suspended by the administrator.
+  pub fn is_service_suspended(&self, service_account_id: AccountId) -> bool {
+    self.suspended_services.contains(&service_account_id)
+  }
+
+  /// Returns the expiration timestamp of the temporary service account grant, 'None' if permanent.
+  pub fn get_service_expiry(&self, service_account_id: AccountId) -> Option<u64> {
+    self.service_expirations.get(&service_account_id)
+  }
+
+  /// Returns all service account IDs, use `get_service_accounts_detailed` for large sets.
+  pub fn get_all_service_accounts(&self) -> Vec<AccountId> {
+    if self.service_accounts.len() > MAX_ALL_SERVICE_ACCOUNTS {
+      env::panic_str("Too many service accounts, use get_service_accounts_detailed");
+    }
+    self.service_accounts.to_vec()
+  }
+
+  /// Returns a page of service account IDs with their labels, empty if no label is set.
+  pub fn get_service_accounts_detailed(&self, from_index: u64, limit: u64) -> Vec<(AccountId, String)> {
+    self
+      .service_accounts
+      .iter()
+      .skip(from_index as usize)
+      .take(limit.min(MAX_PAGE_LIMIT) as usize)
+      .map(|service_account_id| {
+        let label = self.service_labels.get(&service_account_id).unwrap_or_default();
+        (service_account_id, label)
+      })
+      .collect()
+  }
+
+  /// Returns the number of accounts whitelisted by the service account.
+  pub fn get_service_approval_count(&self, service_account_id: AccountId) -> u64 {
+    self.approval_counts.get(&service_account_id).unwrap_or(0)
+  }
+
+  /// Returns the first registered public key for the applicant's account
   pub fn get_applicant_pk(&self, applicant_account_id: AccountId) -> Option<PublicKey> {
-    self.applicants.get(&applicant_account_id)
+    self
+      .applicants
+      .get(&applicant_account_id)
+      .and_then(|applicant| applicant.pks.into_iter().next())
   }
 
-  /// Returns 'true' if the given account ID is whitelisted.
-  pub fn is_whitelisted(&self, account_id: AccountId) -> bool {
-    self.whitelist.contains(&account_id)
+  /// Returns all registered public keys for the applicant's account
+  pub fn get_applicant_pks(&self, applicant_account_id: AccountId) -> Vec<PublicKey> {
+    self
+      .applicants
+      .get(&applicant_account_id)
+      .map(|applicant| applicant.pks)
+      .unwrap_or_default()
   }
 
-  /**
-    Administrator
-  **/
+  /// Returns the applicant account ID registered with the given public key.
+  pub fn get_account_by_pk(&self, pk: PublicKey) -> Option<AccountId> {
+    self.pk_index.get(&pk)
+  }
 
-  /// Adds the given service account ID.
-  pub fn add_service_account(&mut self, service_account_id: AccountId) -> bool {
-    self.assert_called_by_admin();
-    self.service_accounts.insert(&service_account_id)
+  /// Returns 'true' if the given public key belongs to a whitelisted account.
+  pub fn is_pk_whitelisted(&self, pk: PublicKey) -> bool {
+    self
+      .whitelisted_pk_index
+      .get(&pk)
+      .map_or(false, |account_id| self.internal_is_active(&account_id))
   }
 
-  /// Removes the given service account ID.
-  pub fn remove_service_account(&mut self, service_account_id: AccountId) -> bool {
-    self.assert_called_by_admin();
-    self.service_accounts.remove(&service_account_id)
+  /// Returns the maximum number of concurrent applicants.
+  pub fn get_max_applicants(&self) -> Option<u64> {
+    self.max_applicants
   }
 
-  /**
-    Applicant
-  **/
+  /// Returns the maximum number of accounts a single service account can whitelist per window.
+  pub fn get_service_rate_limit(&self) -> Option<u64> {
+    self.service_rate_limit
+  }
 
-  /// Storing the public key of the applicant's account ID.
-  pub fn register_applicant(&mut self) -> Option<PublicKey> {
-    let applicant_account_id = env::signer_account_id();
-    if self.applicants.contains_key(&applicant_account_id) {
-      env::panic_str("Applicant account ID is already exists");
+  /// Returns the time window of the per-service approval rate limit.
+  pub fn get_service_rate_window(&self) -> u64 {
+    self.service_rate_window_ns
+  }
+
+  /// Returns the number of applicants that can still register, 'None' if unlimited.
+  pub fn get_remaining_applicant_capacity(&self) -> Option<u64> {
+    self
+      .max_applicants
+      .map(|max_applicants| max_applicants.saturating_sub(self.applicants.len()))
+  }
+
+  /// Returns the oldest applicants with their first registered public keys in registration order.
+  pub fn get_next_applicants(&self, limit: u64) -> Vec<(AccountId, PublicKey)> {
+    self
+      .applicant_queue
+      .iter()
+      .take(limit.min(MAX_PAGE_LIMIT) as usize)
+      .filter_map(|(_, account_id)| {
+        let pk = self.get_applicant_pk(account_id.clone())?;
+        Some((account_id, pk))
+      })
+      .collect()
+  }
+
+  /// Returns the time elapsed since the applicant's registration.
+  pub fn get_applicant_age(&self, applicant_account_id: AccountId) -> Option<u64> {
+    self
+      .applicants
+      .get(&applicant_account_id)
+      .map(|applicant| env::block_timestamp().saturating_sub(applicant.submitted_at))
+  }
+
+  /// Returns the time during which the KYC verification is valid, unlimited if zero.
+  pub fn get_kyc_validity(&self) -> u64 {
+    self.kyc_validity_ns
+  }
+
+  /// Returns the block timestamp at which the KYC verification of the account expires.
+  pub fn get_kyc_expiry(&self, account_id: AccountId) -> Option<u64> {
+    self.whitelist.get(&account_id).and_then(|record| record.expires_at)
+  }
+
+  /// Returns the time after which a pending applicant can be expired.
+  pub fn get_applicant_ttl(&self) -> u64 {
+    self.applicant_ttl_ns
+  }
+
+  /// Returns the time during which a rejected applicant cannot register again.
+  pub fn get_reject_cooldown(&self) -> u64 {
+    self.reject_cooldown_ns
+  }
+
+  /// Returns an upper-bound estimate of the storage deposit required to register a new applicant.
+  /// The deposit attached to `register_applicant` must also include the registration fee.
+  pub fn get_applicant_storage_cost(&self) -> U128 {
+    U128(Balance::from(APPLICANT_STORAGE_USAGE) * env::storage_byte_cost())
+  }
+
+  /// Returns the flat fee charged on the applicant registration.
+  pub fn get_registration_fee(&self) -> U128 {
+    U128(self.registration_fee)
+  }
+
+  /// Returns the registration fees available for withdrawal.
+  pub fn get_collected_fees(&self) -> U128 {
+    U128(self.collected_fees)
+  }
+
+  /// Returns the number of pending applicants.
+  pub fn get_applicants_count(&self) -> u64 {
+    self.applicants.len()
+  }
+
+  /// Returns 'true' if the given account ID is whitelisted for the default purpose and not frozen.
+  pub fn is_whitelisted(&self, account_id: AccountId) -> bool {
+    self.is_whitelisted_for_purpose(DEFAULT_PURPOSE.to_string(), account_id)
+  }
+
+  /// Returns the KYC status of the account.
+  pub fn get_account_status(&self, account_id: AccountId) -> AccountStatus {
+    if self.internal_is_active(&account_id) {
+      AccountStatus::Whitelisted
+    } else if self.frozen.contains(&account_id) {
+      AccountStatus::Frozen
+    } else if let Some(record) = self.whitelist.get(&account_id) {
+      if env::block_timestamp() < record.valid_from {
+        AccountStatus::Scheduled
+      } else {
+        AccountStatus::Expired
+      }
+    } else if self.applicants.get(&account_id).is_some() {
+      AccountStatus::Applicant
+    } else {
+      AccountStatus::Unknown
     }
-    if self.is_whitelisted(applicant_account_id.clone()) {
-      env::panic_str("Account ID is already whitelisted");
+  }
+
+  /// Returns the KYC statuses of the given accounts in the input order.
+  pub fn get_account_statuses(&self, account_ids: Vec<AccountId>) -> Vec<AccountStatus> {
+    self.assert_batch_size(account_ids.len());
+    account_ids
+      .into_iter()
+      .map(|account_id| self.get_account_status(account_id))
+      .collect()
+  }
+
+  /// Returns a page of applicants followed by whitelisted accounts with their KYC statuses.
+  pub fn get_directory(&self, from_index: u64, limit: u64) -> Vec<(AccountId, AccountStatus)> {
+    let whitelist_from_index = from_index.saturating_sub(self.applicants.len());
+    self
+      .applicants
+      .keys()
+      .skip(from_index as usize)
+      .chain(self.whitelist.keys().skip(whitelist_from_index as usize))
+      .take(limit.min(MAX_PAGE_LIMIT) as usize)
+      .map(|account_id| {
+        let status = self.get_account_status(account_id.clone());
+        (account_id, status)
+      })
+      .collect()
+  }
+
+  /// Returns the KYC status of the calling account.
+  /// Transaction-only, including cross-contract calls: the predecessor account is unavailable in view calls,
+  /// so frontends should view `get_account_status` with the connected account ID instead.
+  pub fn whoami(&self) -> AccountStatus {
+    self.get_account_status(env::predecessor_account_id())
+  }
+
+  /// Returns 'true' if the validity window of the whitelisted account includes the given timestamp.
+  /// Evaluates only the stored window and does not depend on the current block timestamp.
+  pub fn is_whitelisted_at(&self, account_id: AccountId, timestamp: u64) -> bool {
+    self
+      .whitelist
+      .get(&account_id)
+      .map_or(false, |record| record.is_valid_at(timestamp))
+  }
+
+  /// Returns 'true' if the given account ID is frozen.
+  pub fn is_frozen(&self, account_id: AccountId) -> bool {
+    self.frozen.contains(&account_id)
+  }
+
+  /// Returns 'true' if the given account ID is flagged for re-verification.
+  pub fn needs_reverification(&self, account_id: AccountId) -> bool {
+    self.needs_reverification.contains(&account_id)
+  }
+
+  /// Returns a deterministic hash of the whitelisted account IDs, independent of insertion order.
+  /// The hash is chained with `sha256` over the sorted Borsh-serialized account IDs.
+  /// It is O(n) in the whitelist size and intended for occasional reconciliation, not per-block use.
+  pub fn get_whitelist_hash(&self) -> Base64VecU8 {
+    let mut account_ids: Vec<AccountId> = self.whitelist.keys().collect();
+    account_ids.sort();
+    let hash = account_ids.iter().fold(env::sha256(&[]), |hash, account_id| {
+      let mut data = hash;
+      data.extend(account_id.try_to_vec().unwrap());
+      env::sha256(&data)
+    });
+    Base64VecU8(hash)
+  }
+
+  /// Returns the whitelist entry of the account, 'None' if the account was never whitelisted.
+  pub fn get_whitelist_entry(&self, account_id: AccountId) -> Option<WhitelistEntry> {
+    let record = self.whitelist.get(&account_id)?;
+    Some(WhitelistEntry {
+      verified_by: record.verified_by,
+      referrer: record.referrer,
+      whitelisted_block: record.whitelisted_block,
+      valid_from: record.valid_from,
+      expires_at: record.expires_at,
+      first_seen: record.first_seen,
+      updated_at: record.updated_at,
+      frozen: self.frozen.contains(&account_id),
+      is_active: self.internal_is_active(&account_id),
+      account_id,
+    })
+  }
+
+  /// Returns the service account ID that verified the whitelisted account.
+  pub fn get_verifier_of(&self, account_id: AccountId) -> Option<AccountId> {
+    self.whitelist.get(&account_id).and_then(|record| record.verified_by)
+  }
+
+  /// Returns the block timestamp at which the account was first whitelisted.
+  pub fn get_first_seen(&self, account_id: AccountId) -> Option<u64> {
+    self.whitelist.get(&account_id).map(|record| record.first_seen)
+  }
+
+  /// Returns the block timestamp of the last change of the whitelist record.
+  pub fn get_updated_at(&self, account_id: AccountId) -> Option<u64> {
+    self.whitelist.get(&account_id).map(|record| record.updated_at)
+  }
+
+  /// Returns the block height at which the account was whitelisted.
+  pub fn get_whitelisted_block(&self, account_id: AccountId) -> Option<u64> {
+    self.whitelist.get(&account_id).map(|record| record.whitelisted_block)
+  }
+
+  /// Returns the account ID that referred the whitelisted account.
+  pub fn get_referrer_of(&self, account_id: AccountId) -> Option<AccountId> {
+    self.whitelist.get(&account_id).and_then(|record| record.referrer)
+  }
+
+  /// Returns the applicant details including the contact handle, callable only by service accounts.
+  /// Transaction-only, since the predecessor account is unavailable in view calls. The gate is a convenience,
+  /// not access control: the stored contact is readable by anyone via `view_state`.
+  pub fn get_applicant(&self, account_id: AccountId) -> Option<ApplicantView> {
+    self.assert_called_by_service();
+    let applicant = self.applicants.get(&account_id)?;
+    Some(ApplicantView {
+      account_id,
+      pks: applicant.pks,
+      submitted_at: applicant.submitted_at,
+      referrer: applicant.referrer,
+      contact: applicant.contact,
+    })
+  }
+
+  /// Returns the internal note attached to the whitelisted account.
+  pub fn get_account_note(&self, account_id: AccountId) -> Option<String> {
+    self.whitelist.get(&account_id).and_then(|record| record.note)
+  }
+
+  /// Returns the maximum number of whitelisted accounts.
+  pub fn get_max_whitelist_size(&self) -> Option<u64> {
+    self.max_whitelist_size
+  }
+
+  /// Returns registered whitelist purposes, besides the default one.
+  pub fn get_purposes(&self) -> Vec<String> {
+    self.purposes.to_vec()
+  }
+
+  /// Returns 'true' if the account ID is whitelisted for the given purpose.
+  /// Each purpose is an independent whitelist, the default purpose is the KYC whitelist.
+  pub fn is_whitelisted_for_purpose(&self, purpose: String, account_id: AccountId) -> bool {
+    self.assert_known_purpose(&purpose);
+    if purpose == DEFAULT_PURPOSE {
+      return self.internal_is_active(&account_id);
     }
-    self.applicants.insert(&applicant_account_id, &env::signer_account_pk())
+    self
+      .purpose_whitelist
+      .get(&(purpose, account_id))
+      .map_or(false, |record| record.is_valid_at(env::block_timestamp()))
   }
 
-  /// Removes applicant account ID information.
-  pub fn remove_applicant(&mut self) -> Option<PublicKey> {
-    self.internal_remove_applicant(env::signer_account_id())
+  /// Returns 'true' if the removed account ID can still be restored.
+  pub fn is_restorable(&self, account_id: AccountId) -> bool {
+    self.internal_get_restorable(&account_id).is_some()
+  }
+
+  /// Returns the curve type bytes of the public keys accepted at the applicant registration.
+  pub fn get_allowed_curves(&self) -> Vec<u8> {
+    self.allowed_curves.clone()
+  }
+
+  /// Returns 'true' if implicit account IDs can be whitelisted.
+  pub fn get_allow_implicit_accounts(&self) -> bool {
+    self.allow_implicit_accounts
+  }
+
+  /// Returns the number of accounts ever added to the whitelist of any purpose, including removed ones.
+  pub fn get_total_approvals(&self) -> u64 {
+    self.total_approvals
+  }
+
+  /// Returns the maximum number of entries accepted by batch methods.
+  pub fn get_max_batch_size(&self) -> u64 {
+    self.max_batch_size
+  }
+
+  /// Returns 'true' if the contract is in read-only mode.
+  pub fn is_read_only(&self) -> bool {
+    self.read_only
+  }
+
+  /// Returns 'true' if new applicant registrations are paused.
+  pub fn is_registration_paused(&self) -> bool {
+    self.registration_paused
+  }
+
+  /// Returns 'true' if accounts verified by service accounts need the final administrator approval.
+  pub fn get_require_admin_approval(&self) -> bool {
+    self.require_admin_approval
+  }
+
+  /// Returns the number of verified accounts pending the administrator approval.
+  pub fn get_pending_whitelist_count(&self) -> u64 {
+    self.pending_whitelist.len()
+  }
+
+  /// Returns a page of verified account IDs pending the administrator approval.
+  pub fn get_pending_whitelist(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+    self
+      .pending_whitelist
+      .keys()
+      .skip(from_index as usize)
+      .take(limit.min(MAX_PAGE_LIMIT) as usize)
+      .collect()
+  }
+
+  /// Returns 'true' if whitelisting by service accounts is paused.
+  pub fn is_whitelisting_paused(&self) -> bool {
+    self.whitelisting_paused
+  }
+
+  /// Returns 'true' if the given public key belongs to a guardian.
+  pub fn is_guardian(&self, pk: PublicKey) -> bool {
+    self.guardians.contains(&pk)
+  }
+
+  /// Returns the number of guardian confirmations required for a sensitive admin action.
+  pub fn get_guardian_threshold(&self) -> u8 {
+    self.guardian_threshold
+  }
+
+  /// Returns the number of confirmations of the action hash by current guardians.
+  pub fn get_confirmations(&self, action_hash: Base64VecU8) -> u8 {
+    self.internal_count_confirmations(&action_hash.0) as u8
+  }
+
+  /// Returns the action hash guardians confirm before the service account ID is added.
+  pub fn get_add_service_account_hash(&self, service_account_id: AccountId) -> Base64VecU8 {
+    Base64VecU8(Self::add_service_account_hash(&service_account_id))
+  }
+
+  /// Returns the action hash guardians confirm before the guardian public key is added.
+  pub fn get_add_guardian_hash(&self, pk: PublicKey) -> Base64VecU8 {
+    Base64VecU8(Self::guardian_action_hash("add_guardian", &String::from(&pk)))
+  }
+
+  /// Returns the action hash guardians confirm before the guardian public key is removed.
+  pub fn get_remove_guardian_hash(&self, pk: PublicKey) -> Base64VecU8 {
+    Base64VecU8(Self::guardian_action_hash("remove_guardian", &String::from(&pk)))
+  }
+
+  /// Returns the action hash guardians confirm before the guardian threshold is set to the given value.
+  pub fn get_set_guardian_threshold_hash(&self, guardian_threshold: u8) -> Base64VecU8 {
+    Base64VecU8(Self::guardian_action_hash("set_guardian_threshold", &guardian_threshold.to_string()))
+  }
+
+  /// Returns the action hash guardians confirm before all service accounts are replaced with the given ones.
+  pub fn get_replace_service_accounts_hash(&self, new_services: Vec<AccountId>) -> Base64VecU8 {
+    Base64VecU8(Self::replace_service_accounts_hash(&new_services))
+  }
+
+  /// Returns the delegated administrator public key with the timestamp until which it is valid.
+  pub fn get_delegated_admin(&self) -> Option<(PublicKey, u64)> {
+    self.delegated_admin.clone()
+  }
+
+  /// Returns the pending time-locked admin action with the timestamp after which it can be executed.
+  pub fn get_pending_admin_action(&self) -> Option<(AdminAction, u64)> {
+    self.pending_admin_action.clone()
+  }
+
+  /// Returns the number of accounts that can still be whitelisted, 'None' if unlimited.
+  pub fn get_remaining_capacity(&self) -> Option<u64> {
+    self
+      .max_whitelist_size
+      .map(|max_whitelist_size| max_whitelist_size.saturating_sub(self.whitelist.len()))
+  }
+
+  /**
+    Administrator
+  **/
+
+  /// Sets the maximum number of whitelisted accounts, 'None' removes the limit.
+  pub fn set_max_whitelist_size(&mut self, max_whitelist_size: Option<u64>) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.max_whitelist_size = max_whitelist_size;
+  }
+
+  /// Queues the sensitive admin action to be executed after the given timestamp.
+  /// The timestamp must be at least 24 hours ahead of the current block.
+  pub fn queue_admin_action(&mut self, action: AdminAction, execute_after: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if self.pending_admin_action.is_some() {
+      env::panic_str("Admin action is already pending");
+    }
+    if execute_after < env::block_timestamp() + ADMIN_ACTION_DELAY_NS {
+      env::panic_str("Admin action must be delayed by at least 24 hours");
+    }
+    match &action {
+      AdminAction::RenounceAdmin { confirm } => Self::assert_confirmation(confirm),
+      AdminAction::ReplaceServiceAccounts { new_services } => self.assert_batch_size(new_services.len()),
+      AdminAction::RemoveServiceAccount { .. } => {}
+    }
+    self.log_event(
+      "admin_action_queued",
+      json!({
+        "action": action,
+        "execute_after": execute_after,
+      }),
+    );
+    self.pending_admin_action = Some((action, execute_after));
+  }
+
+  /// Executes the pending admin action once its timelock has passed.
+  pub fn execute_admin_action(&mut self) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    let (action, execute_after) = self
+      .pending_admin_action
+      .take()
+      .unwrap_or_else(|| env::panic_str("No pending admin action"));
+    if env::block_timestamp() < execute_after {
+      env::panic_str("Admin action is time-locked");
+    }
+    self.log_event("admin_action_executed", json!({ "action": action }));
+    match action {
+      AdminAction::RemoveServiceAccount { service_account_id } => {
+        self.internal_remove_service_account(&service_account_id);
+      }
+      AdminAction::RenounceAdmin { confirm } => {
+        Self::assert_confirmation(&confirm);
+        self.admin_renounced = true;
+        self.log_event("admin_renounced", json!({ "admin_pk": self.admin_pk }));
+      }
+      AdminAction::ReplaceServiceAccounts { new_services } => {
+        self.assert_confirmed_by_guardians(Self::replace_service_accounts_hash(&new_services));
+        self.internal_replace_service_accounts(new_services);
+      }
+    }
+  }
+
+  /// Cancels the pending admin action.
+  pub fn cancel_admin_action(&mut self) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if self.pending_admin_action.take().is_none() {
+      env::panic_str("No pending admin action");
+    }
+  }
+
+  /// Whitelists the given account IDs directly, bypassing the applicant flow.
+  /// Already whitelisted account IDs abort the whole batch if strict, otherwise they are skipped.
+  /// Returns the number of newly whitelisted accounts.
+  pub fn admin_seed_whitelist(&mut self, account_ids: Vec<AccountId>, strict: bool) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(account_ids.len());
+    let mut seeded = Vec::new();
+    for account_id in account_ids {
+      if self.whitelist.get(&account_id).is_none() {
+        self.internal_delete_applicant_with_refund(&account_id);
+        self.internal_insert_account(&account_id, self.internal_new_record(None));
+        seeded.push(account_id);
+      } else if strict {
+        env::panic_str("Account ID is already whitelisted");
+      }
+    }
+    if !seeded.is_empty() {
+      self.log_event("whitelist_seeded", json!({ "account_ids": seeded }));
+    }
+    seeded.len() as u64
+  }
+
+  /// Moves the given whitelisted accounts and applicants of the first contract release into the current state.
+  /// Account IDs that cannot be migrated abort the whole batch if strict, otherwise they are skipped.
+  /// Returns the number of migrated account IDs.
+  pub fn migrate_legacy_accounts(&mut self, account_ids: Vec<AccountId>, strict: bool) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(account_ids.len());
+    let mut legacy = LegacySmartWhitelistContract::new(self.admin_pk.clone());
+    let mut migrated = Vec::new();
+    for account_id in account_ids {
+      let whitelisted = legacy.whitelist.remove(&account_id);
+      let applicant_pk = legacy.applicants.remove(&account_id);
+      if self.whitelist.get(&account_id).is_some() {
+        if strict {
+          env::panic_str("Legacy account ID cannot be migrated");
+        }
+        continue;
+      }
+      if whitelisted {
+        self.internal_delete_applicant_with_refund(&account_id);
+        self.internal_insert_account(&account_id, self.internal_new_record(None));
+        migrated.push(account_id);
+      } else if let Some(pk) = applicant_pk.filter(|_| self.applicants.get(&account_id).is_none()) {
+        self.internal_insert_applicant_for(&account_id, &pk);
+        migrated.push(account_id);
+      } else if strict {
+        env::panic_str("Legacy account ID cannot be migrated");
+      }
+    }
+    if !migrated.is_empty() {
+      self.log_event("legacy_accounts_migrated", json!({ "account_ids": migrated }));
+    }
+    migrated.len() as u64
+  }
+
+  /// Removes up to `limit` accounts from the whitelist in an emergency, the confirmation must match
+  /// the contract account ID. Call it again until the whole whitelist is cleared.
+  /// Returns the number of accounts left in the whitelist.
+  pub fn admin_clear_whitelist(&mut self, confirm: AccountId, limit: u64) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    Self::assert_confirmation(&confirm);
+    self.assert_batch_size(limit as usize);
+    let account_ids: Vec<AccountId> = self.whitelist.keys().take(limit as usize).collect();
+    for account_id in account_ids.iter() {
+      self.internal_remove_account(account_id);
+    }
+    let remaining = self.whitelist.len();
+    self.log_event(
+      "whitelist_cleared",
+      json!({
+        "count": account_ids.len(),
+        "remaining": remaining,
+      }),
+    );
+    remaining
+  }
+
+  /// Sets the time during which the KYC verification is valid, unlimited if zero.
+  /// Applies only to accounts whitelisted afterward.
+  pub fn set_kyc_validity(&mut self, kyc_validity_ns: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.kyc_validity_ns = kyc_validity_ns;
+  }
+
+  /// Sets the time after which a pending applicant can be expired.
+  pub fn set_applicant_ttl(&mut self, applicant_ttl_ns: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.applicant_ttl_ns = applicant_ttl_ns;
+  }
+
+  /// Whitelists the given account ID directly, even if it never registered as an applicant.
+  /// An already whitelisted account keeps its record and 'false' is returned.
+  pub fn admin_add_account(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if self.whitelist.get(&account_id).is_some() {
+      return false;
+    }
+    self.internal_delete_applicant_with_refund(&account_id);
+    self.internal_insert_account(&account_id, self.internal_new_record(None));
+    self.log_event(
+      "account_whitelisted",
+      json!({
+        "account_id": account_id,
+        "source": "admin",
+      }),
+    );
+    true
+  }
+
+  /// Sets the time during which a rejected applicant cannot register again.
+  pub fn set_reject_cooldown(&mut self, reject_cooldown_ns: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.reject_cooldown_ns = reject_cooldown_ns;
+  }
+
+  /// Removes the given applicants, unknown account IDs abort the whole batch if strict, otherwise they are skipped.
+  /// Returns the number of removed applicants.
+  pub fn admin_remove_applicants(&mut self, account_ids: Vec<AccountId>, strict: bool) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(account_ids.len());
+    let mut removed = Vec::new();
+    for account_id in account_ids {
+      if self.internal_delete_applicant_with_refund(&account_id).is_some() {
+        removed.push(account_id);
+      } else if strict {
+        env::panic_str("Unknown applicant");
+      }
+    }
+    if !removed.is_empty() {
+      self.log_event("applicants_removed", json!({ "account_ids": removed }));
+    }
+    removed.len() as u64
+  }
+
+  /// Removes applicant queue entries of the given account IDs that have no matching applicant.
+  /// Returns the number of cleaned orphans.
+  pub fn reconcile_applicants(&mut self, account_ids: Vec<AccountId>) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(account_ids.len());
+    let orphans: Vec<u64> = self
+      .applicant_queue
+      .iter()
+      .filter(|(queue_position, account_id)| {
+        account_ids.contains(account_id)
+          && self
+            .applicants
+            .get(account_id)
+            .map_or(true, |applicant| applicant.queue_position != *queue_position)
+      })
+      .map(|(queue_position, _)| queue_position)
+      .collect();
+    for queue_position in orphans.iter() {
+      self.applicant_queue.remove(queue_position);
+    }
+    orphans.len() as u64
+  }
+
+  /// Removes public key index entries of the given public keys that have no matching applicant.
+  /// Returns the number of cleaned orphans.
+  pub fn reconcile_applicant_pks(&mut self, pks: Vec<PublicKey>) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(pks.len());
+    let mut cleaned = 0;
+    for pk in pks {
+      let is_orphan = self.pk_index.get(&pk).map_or(false, |account_id| {
+        self
+          .applicants
+          .get(&account_id)
+          .map_or(true, |applicant| !applicant.pks.contains(&pk))
+      });
+      if is_orphan {
+        self.pk_index.remove(&pk);
+        cleaned += 1;
+      }
+    }
+    cleaned
+  }
+
+  /// Sets the maximum number of concurrent applicants, 'None' removes the limit.
+  pub fn set_max_applicants(&mut self, max_applicants: Option<u64>) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.max_applicants = max_applicants;
+  }
+
+  /// Sets the per-service approval rate limit within the given time window, 'None' removes the limit.
+  pub fn set_service_rate_limit(&mut self, service_rate_limit: Option<u64>, service_rate_window_ns: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if service_rate_window_ns == 0 {
+      env::panic_str("Rate limit window must be positive");
+    }
+    self.service_rate_limit = service_rate_limit;
+    self.service_rate_window_ns = service_rate_window_ns;
+  }
+
+  /// Sets the curve type bytes of the public keys accepted at the applicant registration,
+  /// 0 for ed25519 and 1 for secp256k1.
+  pub fn set_allowed_curves(&mut self, allowed_curves: Vec<u8>) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.allowed_curves = allowed_curves;
+  }
+
+  /// Allows or forbids whitelisting implicit account IDs.
+  pub fn set_allow_implicit_accounts(&mut self, allow_implicit_accounts: bool) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.allow_implicit_accounts = allow_implicit_accounts;
+  }
+
+  /// Suspends the service account, keeping its configuration until it is resumed.
+  pub fn suspend_service_account(&mut self, service_account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if !self.service_accounts.contains(&service_account_id) {
+      env::panic_str("Unknown service account");
+    }
+    let suspended = self.suspended_services.insert(&service_account_id);
+    if suspended {
+      self.log_event("service_account_suspended", json!({ "service_account_id": service_account_id }));
+    }
+    suspended
+  }
+
+  /// Resumes the suspended service account.
+  pub fn resume_service_account(&mut self, service_account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    let resumed = self.suspended_services.remove(&service_account_id);
+    if resumed {
+      self.log_event("service_account_resumed", json!({ "service_account_id": service_account_id }));
+    }
+    resumed
+  }
+
+  /// Sets the maximum number of entries accepted by batch methods.
+  pub fn set_max_batch_size(&mut self, max_batch_size: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if max_batch_size == 0 {
+      env::panic_str("Max batch size must be positive");
+    }
+    self.max_batch_size = max_batch_size;
+  }
+
+  /// Enables or disables the read-only mode freezing all state changes, views keep working.
+  pub fn set_read_only(&mut self, read_only: bool) {
+    self.assert_called_by_admin();
+    self.read_only = read_only;
+  }
+
+  /// Pauses or resumes new applicant registrations.
+  pub fn set_registration_paused(&mut self, paused: bool) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.registration_paused = paused;
+  }
+
+  /// Sets the consumer contract notified about accounts whitelisted by service accounts, disabled if not set.
+  pub fn set_notify_contract(&mut self, notify_contract: Option<AccountId>) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.notify_contract = notify_contract;
+  }
+
+  /// Sets the whitelist contract checked for account IDs that are not whitelisted locally, disabled if not set.
+  pub fn set_fallback_whitelist(&mut self, fallback_whitelist: Option<AccountId>) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.fallback_whitelist = fallback_whitelist;
+  }
+
+  /// Sets the standard name and version of the emitted events.
+  pub fn set_event_standard(&mut self, event_standard: String, event_version: String) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if event_standard.is_empty() || event_version.is_empty() {
+      env::panic_str("Event standard and version must not be empty");
+    }
+    self.event_standard = event_standard;
+    self.event_version = event_version;
+  }
+
+  /// Enables or disables the final administrator approval of accounts verified by service accounts.
+  pub fn set_require_admin_approval(&mut self, require_admin_approval: bool) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.require_admin_approval = require_admin_approval;
+  }
+
+  /// Whitelists the verified account pending the administrator approval.
+  pub fn admin_approve_pending(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    let record = self
+      .pending_whitelist
+      .remove(&account_id)
+      .unwrap_or_else(|| env::panic_str("Account ID is not pending approval"));
+    let service_account_id = record.verified_by.clone();
+    let added = self.internal_add_account(&account_id, record);
+    if added {
+      self.internal_notify_whitelisted(&account_id);
+      self.log_event(
+        "account_whitelisted",
+        json!({
+          "account_id": account_id,
+          "source": "admin_approval",
+          "service_account_id": service_account_id,
+        }),
+      );
+    }
+    added
+  }
+
+  /// Discards the verified account pending the administrator approval.
+  pub fn admin_reject_pending(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    let rejected = self.pending_whitelist.remove(&account_id).is_some();
+    if rejected {
+      self.log_event("pending_account_rejected", json!({ "account_id": account_id }));
+    }
+    rejected
+  }
+
+  /// Pauses or resumes whitelisting by service accounts.
+  pub fn set_whitelisting_paused(&mut self, paused: bool) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.whitelisting_paused = paused;
+  }
+
+  /// Adds the guardian public key.
+  /// Requires guardian confirmations of `get_add_guardian_hash` when the guardian threshold is set.
+  pub fn add_guardian(&mut self, pk: PublicKey) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_confirmed_by_guardians(Self::guardian_action_hash("add_guardian", &String::from(&pk)));
+    self.guardians.insert(&pk)
+  }
+
+  /// Removes the guardian public key, keeping at least as many guardians as the guardian threshold.
+  /// Requires guardian confirmations of `get_remove_guardian_hash` when the guardian threshold is set.
+  pub fn remove_guardian(&mut self, pk: PublicKey) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if self.guardians.contains(&pk) && self.guardians.len() <= self.guardian_threshold as u64 {
+      env::panic_str("Not enough guardians left for the guardian threshold");
+    }
+    self.assert_confirmed_by_guardians(Self::guardian_action_hash("remove_guardian", &String::from(&pk)));
+    self.guardians.remove(&pk)
+  }
+
+  /// Sets the number of guardian confirmations required for a sensitive admin action, zero disables it.
+  /// The threshold cannot exceed the number of guardians.
+  /// Requires guardian confirmations of `get_set_guardian_threshold_hash` when the guardian threshold is set.
+  pub fn set_guardian_threshold(&mut self, guardian_threshold: u8) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if guardian_threshold as u64 > self.guardians.len() {
+      env::panic_str("Guardian threshold exceeds the number of guardians");
+    }
+    self.assert_confirmed_by_guardians(Self::guardian_action_hash(
+      "set_guardian_threshold",
+      &guardian_threshold.to_string(),
+    ));
+    self.guardian_threshold = guardian_threshold;
+  }
+
+  /// Delegates the administration to the backup public key until the given block timestamp.
+  /// Can only be called with the primary administrator key.
+  pub fn delegate_admin(&mut self, pk: PublicKey, until: u64) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if env::signer_account_pk() != self.admin_pk {
+      env::panic_str("Can only be called by whitelist administrator");
+    }
+    if until <= env::block_timestamp() {
+      env::panic_str("Delegation must end in the future");
+    }
+    self.delegated_admin = Some((pk, until));
+  }
+
+  /// Revokes the administration delegation.
+  pub fn revoke_admin_delegation(&mut self) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.delegated_admin = None;
+  }
+
+  /// Sets the flat fee charged on the applicant registration.
+  pub fn set_registration_fee(&mut self, registration_fee: U128) {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.registration_fee = registration_fee.0;
+  }
+
+  /// Transfers the given amount of the collected registration fees to the treasury account ID.
+  /// Storage deposits held for applicants are never withdrawn.
+  /// The fees are restored in `on_fees_withdrawn` if the transfer fails.
+  pub fn withdraw_fees(&mut self, amount: U128, to: AccountId) -> Promise {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if amount.0 > self.collected_fees {
+      env::panic_str("Not enough collected fees");
+    }
+    self.collected_fees -= amount.0;
+    Promise::new(to)
+      .transfer(amount.0)
+      .then(ext_self::on_fees_withdrawn(amount, env::current_account_id(), 0, ON_FEES_WITHDRAWN_GAS))
+  }
+
+  /// Adds the given service account ID with a human-readable label and a lifetime approval quota,
+  /// unlimited if zero.
+  /// Requires guardian confirmations of `get_add_service_account_hash` when the guardian threshold is set.
+  pub fn add_service_account(&mut self, service_account_id: AccountId, label: String, quota: u64) -> bool {
+    self.add_service_account_with_metadata(service_account_id, label, quota, None)
+  }
+
+  /// Adds the service account ID with approval power that lapses at the given block timestamp.
+  pub fn add_temporary_service_account(
+    &mut self,
+    service_account_id: AccountId,
+    label: String,
+    quota: u64,
+    expires_at: u64,
+  ) -> bool {
+    self.add_service_account_with_metadata(service_account_id, label, quota, Some(expires_at))
+  }
+
+  /// Adds the service account ID writing all of its per-service fields at once: the label,
+  /// the lifetime approval quota, unlimited if zero, and the optional grant expiration.
+  /// Requires guardian confirmations of `get_add_service_account_hash` when the guardian threshold is set.
+  pub fn add_service_account_with_metadata(
+    &mut self,
+    service_account_id: AccountId,
+    label: String,
+    quota: u64,
+    expires_at: Option<u64>,
+  ) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_confirmed_by_guardians(Self::add_service_account_hash(&service_account_id));
+    if label.chars().count() > MAX_SERVICE_LABEL_LENGTH {
+      env::panic_str("Service label is too long");
+    }
+    if expires_at.map_or(false, |expires_at| expires_at <= env::block_timestamp()) {
+      env::panic_str("Expiration must be in the future");
+    }
+    self.service_labels.insert(&service_account_id, &label);
+    if quota > 0 {
+      self.service_quotas.insert(&service_account_id, &quota);
+    } else {
+      self.service_quotas.remove(&service_account_id);
+    }
+    match expires_at {
+      Some(expires_at) => self.service_expirations.insert(&service_account_id, &expires_at),
+      None => self.service_expirations.remove(&service_account_id),
+    };
+    let added = self.service_accounts.insert(&service_account_id);
+    if added {
+      self.log_event(
+        "service_account_added",
+        json!({
+          "service_account_id": service_account_id,
+          "admin_pk": env::signer_account_pk(),
+        }),
+      );
+    }
+    added
+  }
+
+  /// Replaces all service accounts with the given ones, removing the per-service data of the dropped ones.
+  /// Executes at once only with guardian confirmations of `get_replace_service_accounts_hash`,
+  /// without guardians queue `AdminAction::ReplaceServiceAccounts` through the timelock instead.
+  /// Returns the new number of service accounts.
+  pub fn replace_service_accounts(&mut self, new_services: Vec<AccountId>) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    self.assert_batch_size(new_services.len());
+    if self.guardian_threshold == 0 {
+      env::panic_str("Service accounts can only be replaced through the timelock without guardians");
+    }
+    self.assert_confirmed_by_guardians(Self::replace_service_accounts_hash(&new_services));
+    self.internal_replace_service_accounts(new_services)
+  }
+
+  /// Registers a new whitelist purpose.
+  pub fn create_purpose(&mut self, purpose: String) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_admin();
+    if purpose.is_empty() || purpose == DEFAULT_PURPOSE || purpose.chars().count() > MAX_PURPOSE_LENGTH {
+      env::panic_str("Invalid purpose");
+    }
+    if self.purposes.len() >= MAX_PURPOSES {
+      env::panic_str("Too many purposes");
+    }
+    self.purposes.insert(&purpose)
+  }
+
+  /**
+    Cross-contract
+  **/
+
+  /// Checks the account ID against the given whitelist contract and resolves the result in
+  /// `on_whitelist_checked`. Demonstrates consuming the boolean result of the cross-contract call.
+  /// Must be called in a transaction, since view calls cannot create promises.
+  pub fn check_whitelisted(&self, whitelist_account_id: AccountId, account_id: AccountId) -> Promise {
+    is_whitelisted_promise(whitelist_account_id, account_id)
+      .then(ext_self::on_whitelist_checked(env::current_account_id(), 0, ON_WHITELIST_CHECKED_GAS))
+  }
+
+  /// Callback resolving the result of the cross-contract `is_whitelisted` call.
+  /// Emits no event, since the checked whitelist contract is chosen by the caller.
+  #[private]
+  pub fn on_whitelist_checked(&self, #[callback_unwrap] is_whitelisted: bool) -> bool {
+    is_whitelisted
+  }
+
+  /// Returns 'true' if the account ID is whitelisted locally, otherwise checks it against the fallback whitelist
+  /// contract and resolves the result in `on_fallback_checked`.
+  /// A change method rather than a view, since view calls cannot create promises.
+  pub fn is_whitelisted_with_fallback(&mut self, account_id: AccountId) -> PromiseOrValue<bool> {
+    self.assert_not_read_only();
+    if self.internal_is_active(&account_id) {
+      return PromiseOrValue::Value(true);
+    }
+    match self.fallback_whitelist.clone() {
+      Some(fallback_whitelist) => PromiseOrValue::Promise(
+        is_whitelisted_promise(fallback_whitelist, account_id)
+          .then(ext_self::on_fallback_checked(env::current_account_id(), 0, ON_WHITELIST_CHECKED_GAS)),
+      ),
+      None => PromiseOrValue::Value(false),
+    }
+  }
+
+  /// Callback resolving the result of the cross-contract `is_whitelisted` call to the fallback whitelist.
+  #[private]
+  pub fn on_fallback_checked(&self, #[callback_unwrap] is_whitelisted: bool) -> bool {
+    is_whitelisted
+  }
+
+  /// Callback resolving the fees withdrawal transfer, restores the collected fees if it failed.
+  /// Returns 'true' if the fees were transferred.
+  #[private]
+  pub fn on_fees_withdrawn(&mut self, amount: U128) -> bool {
+    if is_promise_success() {
+      return true;
+    }
+    self.collected_fees += amount.0;
+    self.log_event("fees_withdrawal_failed", json!({ "amount": amount }));
+    false
+  }
+
+  /**
+    Guardian
+  **/
+
+  /// Records the signer guardian's confirmation of the action hash.
+  /// Returns the number of confirmations of the action hash.
+  pub fn confirm_action(&mut self, action_hash: Base64VecU8) -> u8 {
+    self.assert_not_read_only();
+    let guardian_pk = env::signer_account_pk();
+    if !self.guardians.contains(&guardian_pk) {
+      env::panic_str("Can only be called by guardian");
+    }
+    if action_hash.0.len() != 32 {
+      env::panic_str("Invalid action hash");
+    }
+    let mut guardian_pks = self.confirmations.get(&action_hash.0).unwrap_or_default();
+    if !guardian_pks.contains(&guardian_pk) {
+      guardian_pks.push(guardian_pk);
+      self.confirmations.insert(&action_hash.0, &guardian_pks);
+    }
+    guardian_pks.len() as u8
+  }
+
+  /**
+    Applicant
+  **/
+
+  /// Storing the public key of the applicant's account ID.
+  /// Calling it again with another access key appends that key to the applicant's keys.
+  /// The attached deposit must cover the registration fee of a new applicant and the storage of the key.
+  /// The optional referrer must be whitelisted and is recorded only for a new applicant.
+  /// A rejected registration leaves the state untouched.
+  #[payable]
+  pub fn register_applicant(
+    &mut self,
+    referrer: Option<AccountId>,
+    contact: Option<String>,
+  ) -> Result<Option<PublicKey>, ContractError> {
+    self.assert_not_read_only();
+    let applicant_account_id = env::signer_account_id();
+    let pk = env::signer_account_pk();
+    self.check_can_register(&applicant_account_id, &pk)?;
+    if contact.as_ref().map_or(false, |contact| contact.chars().count() > MAX_CONTACT_LENGTH) {
+      return Err(ContractError::ContactTooLong);
+    }
+    if let Some(referrer) = referrer.as_ref() {
+      if !self.internal_is_active(referrer) {
+        return Err(ContractError::ReferrerNotWhitelisted);
+      }
+    }
+    let attached_deposit = env::attached_deposit();
+    if attached_deposit == 0 {
+      return Err(ContractError::DepositRequired);
+    }
+    let initial_storage_usage = env::storage_usage();
+    // The applicant is read once, the insert below hands back the replaced record for the rollback.
+    let (applicant, registration_fee) = match self.applicants.get(&applicant_account_id) {
+      Some(mut applicant) => {
+        if applicant.pks.len() >= MAX_APPLICANT_KEYS {
+          return Err(ContractError::TooManyPublicKeys);
+        }
+        applicant.pks.push(pk.clone());
+        applicant.storage_deposit += attached_deposit;
+        if contact.is_some() {
+          applicant.contact = contact;
+        }
+        (applicant, 0)
+      }
+      None => {
+        self.check_applicant_capacity()?;
+        if attached_deposit < self.registration_fee {
+          return Err(ContractError::InsufficientDeposit);
+        }
+        let applicant = Applicant {
+          pks: vec![pk.clone()],
+          submitted_at: env::block_timestamp(),
+          storage_deposit: attached_deposit.saturating_sub(self.registration_fee),
+          queue_position: self.internal_enqueue_applicant(&applicant_account_id),
+          doc_hash: None,
+          referrer,
+          contact,
+        };
+        (applicant, self.registration_fee)
+      }
+    };
+    let old_applicant = self.applicants.insert(&applicant_account_id, &applicant);
+    self.pk_index.insert(&pk, &applicant_account_id);
+    let storage_cost =
+      Balance::from(env::storage_usage() - initial_storage_usage) * env::storage_byte_cost();
+    if attached_deposit < registration_fee + storage_cost {
+      match old_applicant {
+        Some(old_applicant) => {
+          self.pk_index.remove(&pk);
+          self.applicants.insert(&applicant_account_id, &old_applicant);
+        }
+        None => {
+          self.internal_delete_applicant(&applicant_account_id);
+          self.next_queue_position -= 1;
+        }
+      }
+      return Err(ContractError::InsufficientDeposit);
+    }
+    self.rejected_until.remove(&applicant_account_id);
+    self.collected_fees += registration_fee;
+    self.log_event(
+      "applicant_registered",
+      json!({
+        "account_id": applicant_account_id,
+        "public_key": pk,
+      }),
+    );
+    Ok(old_applicant.and_then(|applicant| applicant.pks.into_iter().next()))
+  }
+
+  /// Replaces the stored public keys of the applicant's account ID with the given one,
+  /// keeping the registration time.
+  pub fn update_applicant_pk(&mut self, new_pk: PublicKey) -> Option<PublicKey> {
+    self.assert_not_read_only();
+    let applicant_account_id = env::signer_account_id();
+    let mut applicant = self
+      .applicants
+      .get(&applicant_account_id)
+      .unwrap_or_else(|| env::panic_str("Unknown applicant"));
+    self.check_supported_curve(&new_pk).unwrap_or_else(|err| err.panic());
+    self.check_pk_not_registered(&new_pk).unwrap_or_else(|err| err.panic());
+    let old_pks = std::mem::replace(&mut applicant.pks, vec![new_pk.clone()]);
+    self.applicants.insert(&applicant_account_id, &applicant);
+    for old_pk in old_pks.iter() {
+      self.pk_index.remove(old_pk);
+    }
+    self.pk_index.insert(&new_pk, &applicant_account_id);
+    self.log_event(
+      "applicant_pk_updated",
+      json!({
+        "account_id": applicant_account_id,
+        "public_key": new_pk,
+      }),
+    );
+    old_pks.into_iter().next()
+  }
+
+  /// Revokes all stored public keys of the applicant's account ID, keeping the registration and queue position.
+  /// The applicant cannot be approved until a new key is set with `update_applicant_pk`.
+  pub fn revoke_applicant_pk(&mut self) -> Option<PublicKey> {
+    self.assert_not_read_only();
+    let applicant_account_id = env::signer_account_id();
+    let mut applicant = self
+      .applicants
+      .get(&applicant_account_id)
+      .unwrap_or_else(|| env::panic_str("Unknown applicant"));
+    let old_pks = std::mem::take(&mut applicant.pks);
+    self.applicants.insert(&applicant_account_id, &applicant);
+    for old_pk in old_pks.iter() {
+      self.pk_index.remove(old_pk);
+    }
+    self.log_event("applicant_pk_revoked", json!({ "account_id": applicant_account_id }));
+    old_pks.into_iter().next()
+  }
+
+  /// Stores the hash of the KYC documents submitted by the applicant.
+  pub fn submit_document_hash(&mut self, doc_hash: Base64VecU8) {
+    self.assert_not_read_only();
+    let applicant_account_id = env::signer_account_id();
+    let mut applicant = self
+      .applicants
+      .get(&applicant_account_id)
+      .unwrap_or_else(|| env::panic_str("Unknown applicant"));
+    if doc_hash.0.len() != DOC_HASH_LENGTH {
+      env::panic_str("Invalid document hash");
+    }
+    applicant.doc_hash = Some(doc_hash.0);
+    self.applicants.insert(&applicant_account_id, &applicant);
+  }
+
+  /// Removes applicant account ID information and refunds the held storage deposit.
+  pub fn remove_applicant(&mut self) -> Option<PublicKey> {
+    self.assert_not_read_only();
+    let applicant_account_id = env::signer_account_id();
+    let applicant = self
+      .internal_delete_applicant_with_refund(&applicant_account_id)
+      .unwrap_or_else(|| env::panic_str("Unknown applicant"));
+    applicant.pks.into_iter().next()
+  }
+
+  /**
+    Service
+  **/
+
+  /// Storing the public key collected off-chain for the applicant's account ID.
+  pub fn register_applicant_for(&mut self, account_id: AccountId, pk: PublicKey) {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    if self.applicants.get(&account_id).is_some() {
+      env::panic_str("Applicant account ID is already exists");
+    }
+    self.check_can_register(&account_id, &pk).unwrap_or_else(|err| err.panic());
+    self.check_applicant_capacity().unwrap_or_else(|err| err.panic());
+    self.internal_insert_applicant_for(&account_id, &pk);
+    self.log_event(
+      "applicant_registered",
+      json!({
+        "account_id": account_id,
+        "public_key": pk,
+        "service_account_id": env::predecessor_account_id(),
+      }),
+    );
+  }
+
+  /// Storing the public keys collected off-chain for the given applicants' account IDs,
+  /// skipping entries that fail any of the registration checks.
+  /// Returns the number of newly registered applicants.
+  pub fn register_applicants_for(&mut self, entries: Vec<(AccountId, PublicKey)>) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.assert_batch_size(entries.len());
+    let mut registered = Vec::new();
+    for (account_id, pk) in entries {
+      let can_register = self.applicants.get(&account_id).is_none()
+        && self.check_can_register(&account_id, &pk).is_ok()
+        && self.check_applicant_capacity().is_ok();
+      if can_register {
+        self.internal_insert_applicant_for(&account_id, &pk);
+        registered.push(account_id);
+      }
+    }
+    if !registered.is_empty() {
+      self.log_event(
+        "applicants_registered",
+        json!({
+          "account_ids": registered,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    registered.len() as u64
+  }
+
+  /// Adds a verified account ID to the whitelist of the default purpose.
+  /// Notifying the consumer contract, if set, costs extra `ON_ACCOUNT_WHITELISTED_GAS`.
+  pub fn add_account(&mut self, account_id: AccountId) -> Result<bool, ContractError> {
+    self.add_account_for_purpose(DEFAULT_PURPOSE.to_string(), account_id)
+  }
+
+  /// Adds a verified account ID to the whitelist if the expected document hash matches
+  /// the hash submitted by the applicant.
+  pub fn add_account_verified(&mut self, account_id: AccountId, expected_doc_hash: Base64VecU8) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    let doc_hash = self.applicants.get(&account_id).and_then(|applicant| applicant.doc_hash);
+    if doc_hash != Some(expected_doc_hash.0) {
+      env::panic_str("Document hash mismatch");
+    }
+    self
+      .internal_approve_applicant(account_id, self.internal_new_record(None))
+      .unwrap_or_else(|err| err.panic())
+  }
+
+  /// Adds a verified account ID to the whitelist with an internal note.
+  pub fn add_account_with_note(&mut self, account_id: AccountId, note: String) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    Self::assert_valid_note(&note);
+    self
+      .internal_approve_applicant(account_id, self.internal_new_record(Some(note)))
+      .unwrap_or_else(|err| err.panic())
+  }
+
+  /// Adds a verified account ID to the whitelist for the validity window from `valid_from`
+  /// until `valid_until`, both inclusive. The record expires right after `valid_until`.
+  pub fn add_account_window(&mut self, account_id: AccountId, valid_from: u64, valid_until: u64) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    if valid_from >= valid_until {
+      env::panic_str("Invalid validity window");
+    }
+    let mut record = WhitelistRecord::new(None);
+    record.valid_from = valid_from;
+    record.expires_at = valid_until.checked_add(1);
+    self
+      .internal_approve_applicant(account_id, record)
+      .unwrap_or_else(|err| err.panic())
+  }
+
+  /// Extends the KYC verification of the whitelisted account by the current KYC validity,
+  /// keeping the rest of its record. Clears the re-verification flag of the account.
+  pub fn renew_account(&mut self, account_id: AccountId) {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    let mut record = self.internal_get_record(&account_id);
+    let now = env::block_timestamp();
+    record.expires_at = if self.kyc_validity_ns > 0 {
+      Some(now + self.kyc_validity_ns)
+    } else {
+      None
+    };
+    record.updated_at = now;
+    self.whitelist.insert(&account_id, &record);
+    self.log_event(
+      "account_renewed",
+      json!({
+        "account_id": account_id,
+        "expires_at": record.expires_at,
+        "service_account_id": env::predecessor_account_id(),
+      }),
+    );
+    if self.needs_reverification.remove(&account_id) {
+      self.log_event(
+        "reverification_cleared",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+  }
+
+  /// Replaces the internal note of the whitelisted account.
+  pub fn set_account_note(&mut self, account_id: AccountId, note: String) {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    Self::assert_valid_note(&note);
+    let mut record = self.internal_get_record(&account_id);
+    record.note = Some(note);
+    record.updated_at = env::block_timestamp();
+    self.whitelist.insert(&account_id, &record);
+  }
+
+  /// Rejects the applicant, who cannot register again until the reject cooldown has passed.
+  /// The held storage deposit is refunded if requested, otherwise it is forfeited to the collected fees.
+  pub fn reject_applicant(&mut self, account_id: AccountId, reason: String, refund: bool) {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    if reason.chars().count() > MAX_REASON_LENGTH {
+      env::panic_str("Reason is too long");
+    }
+    let applicant = self
+      .internal_delete_applicant(&account_id)
+      .unwrap_or_else(|| env::panic_str("Unknown applicant"));
+    if refund {
+      Self::internal_refund_storage_deposit(&account_id, applicant.storage_deposit);
+    } else {
+      self.collected_fees += applicant.storage_deposit;
+    }
+    let rejected_until = env::block_timestamp() + self.reject_cooldown_ns;
+    self.rejected_until.insert(&account_id, &rejected_until);
+    self.log_event(
+      "applicant_rejected",
+      json!({
+        "account_id": account_id,
+        "service_account_id": env::predecessor_account_id(),
+        "reason": reason,
+        "refunded": refund,
+        "had_contact": applicant.contact.is_some(),
+      }),
+    );
+  }
+
+  /// Removes the given applicants that are older than the applicant TTL.
+  /// Account IDs of no stale applicant abort the whole batch if strict, otherwise they are skipped.
+  /// Returns the number of expired applicants.
+  pub fn expire_stale_applicants(&mut self, account_ids: Vec<AccountId>, strict: bool) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.assert_batch_size(account_ids.len());
+    let mut expired = Vec::new();
+    for account_id in account_ids {
+      let is_stale = self
+        .get_applicant_age(account_id.clone())
+        .map_or(false, |age| age > self.applicant_ttl_ns);
+      if is_stale {
+        self.internal_delete_applicant_with_refund(&account_id);
+        expired.push(account_id);
+      } else if strict {
+        env::panic_str("Applicant is not stale");
+      }
+    }
+    if !expired.is_empty() {
+      self.log_event("applicants_expired", json!({ "account_ids": expired }));
+    }
+    expired.len() as u64
+  }
+
+  /// Removes the given account ID from the whitelist of the default purpose for the given reason.
+  pub fn remove_account(&mut self, account_id: AccountId, reason: RemovalReason) -> bool {
+    self.remove_account_for_purpose(DEFAULT_PURPOSE.to_string(), account_id, reason)
+  }
+
+  /// Adds a verified account ID to the whitelist of the given purpose.
+  /// The default purpose whitelists the applicant, other purposes need no applicant.
+  /// Every purpose counts against the quota and the rate limit of the service account and the total approvals.
+  /// Only the default purpose goes through the administrator approval, other purposes are exempt from it.
+  pub fn add_account_for_purpose(&mut self, purpose: String, account_id: AccountId) -> Result<bool, ContractError> {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.assert_known_purpose(&purpose);
+    if purpose == DEFAULT_PURPOSE {
+      return self.internal_approve_applicant(account_id, self.internal_new_record(None));
+    }
+    if self.whitelisting_paused {
+      return Err(ContractError::WhitelistingPaused);
+    }
+    if account_id == env::predecessor_account_id() {
+      return Err(ContractError::SelfWhitelisting);
+    }
+    if self.get_service_remaining_quota(env::predecessor_account_id()) == Some(0) {
+      return Err(ContractError::ServiceQuotaExhausted);
+    }
+    self.assert_valid_account_id(&account_id);
+    let key = (purpose.clone(), account_id.clone());
+    if self.purpose_whitelist.get(&key).is_some() {
+      return Ok(false);
+    }
+    let mut record = self.internal_new_record(None);
+    record.verified_by = Some(env::predecessor_account_id());
+    self.purpose_whitelist.insert(&key, &record);
+    self.internal_record_service_approval(&env::predecessor_account_id());
+    self.total_approvals += 1;
+    self.log_event(
+      "purpose_account_added",
+      json!({
+        "purpose": purpose,
+        "account_id": account_id,
+        "service_account_id": env::predecessor_account_id(),
+      }),
+    );
+    Ok(true)
+  }
+
+  /// Removes the given account ID from the whitelist of the given purpose for the given reason.
+  pub fn remove_account_for_purpose(&mut self, purpose: String, account_id: AccountId, reason: RemovalReason) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.assert_known_purpose(&purpose);
+    if let RemovalReason::Other(reason) = &reason {
+      if reason.chars().count() > MAX_REASON_LENGTH {
+        env::panic_str("Reason is too long");
+      }
+    }
+    if purpose != DEFAULT_PURPOSE {
+      let removed = self.purpose_whitelist.remove(&(purpose.clone(), account_id.clone())).is_some();
+      if removed {
+        self.log_event(
+          "purpose_account_removed",
+          json!({
+            "purpose": purpose,
+            "account_id": account_id,
+            "reason": reason,
+            "service_account_id": env::predecessor_account_id(),
+          }),
+        );
+      }
+      return removed;
+    }
+    let record = self.internal_remove_account(&account_id);
+    let removed = record.is_some();
+    if let Some(record) = record {
+      let removed_account = RemovedAccount {
+        record,
+        removed_at: env::block_timestamp(),
+      };
+      self.removed.insert(&account_id, &removed_account);
+      self.log_event(
+        "account_removed",
+        json!({
+          "account_id": account_id,
+          "reason": reason,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    removed
+  }
+
+  /// Restores the whitelist record of the account ID removed within the retention window.
+  pub fn restore_account(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    let removed_account = self
+      .internal_get_restorable(&account_id)
+      .unwrap_or_else(|| env::panic_str("Account ID cannot be restored"));
+    self.internal_delete_applicant_with_refund(&account_id);
+    let restored = self.internal_add_account(&account_id, removed_account.record);
+    if restored {
+      self.log_event(
+        "account_restored",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    restored
+  }
+
+  /// Permanently deletes the given removed accounts past the retention window.
+  /// Account IDs that cannot be purged abort the whole batch if strict, otherwise they are skipped.
+  /// Returns the number of purged accounts.
+  pub fn purge_removed_accounts(&mut self, account_ids: Vec<AccountId>, strict: bool) -> u64 {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.assert_batch_size(account_ids.len());
+    let mut purged = 0;
+    for account_id in account_ids {
+      if self.removed.get(&account_id).is_some() && self.internal_get_restorable(&account_id).is_none() {
+        self.removed.remove(&account_id);
+        purged += 1;
+      } else if strict {
+        env::panic_str("Removed account cannot be purged");
+      }
+    }
+    purged
+  }
+
+  /// Moves the whitelist record with its flags from the old account ID to the new one.
+  /// A live applicant under the new account ID is removed and its storage deposit refunded.
+  pub fn migrate_account(&mut self, old_account_id: AccountId, new_account_id: AccountId) {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.internal_get_record(&old_account_id);
+    self.assert_valid_account_id(&new_account_id);
+    if self.whitelist.get(&new_account_id).is_some() {
+      env::panic_str("Account ID is already whitelisted");
+    }
+    if self.pending_whitelist.get(&new_account_id).is_some() {
+      env::panic_str("Account ID is pending admin approval");
+    }
+    let frozen = self.frozen.contains(&old_account_id);
+    let needs_reverification = self.needs_reverification.contains(&old_account_id);
+    let purpose_records: Vec<(String, WhitelistRecord)> = self
+      .purposes
+      .iter()
+      .filter_map(|purpose| {
+        let record = self.purpose_whitelist.remove(&(purpose.clone(), old_account_id.clone()))?;
+        Some((purpose, record))
+      })
+      .collect();
+    let mut record = self.internal_remove_account(&old_account_id).unwrap();
+    record.updated_at = env::block_timestamp();
+    self.internal_delete_applicant_with_refund(&new_account_id);
+    self.removed.remove(&new_account_id);
+    for pk in record.pks.iter() {
+      self.whitelisted_pk_index.insert(pk, &new_account_id);
+    }
+    self.whitelist.insert(&new_account_id, &record);
+    for (purpose, record) in purpose_records {
+      self.purpose_whitelist.insert(&(purpose, new_account_id.clone()), &record);
+    }
+    if frozen {
+      self.frozen.insert(&new_account_id);
+    }
+    if needs_reverification {
+      self.needs_reverification.insert(&new_account_id);
+    }
+    self.log_event(
+      "account_migrated",
+      json!({
+        "old_account_id": old_account_id,
+        "new_account_id": new_account_id,
+        "service_account_id": env::predecessor_account_id(),
+      }),
+    );
+  }
+
+  /// Temporarily suspends the whitelisted account ID, keeping its whitelist record.
+  pub fn freeze_account(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.internal_get_record(&account_id);
+    let frozen = self.frozen.insert(&account_id);
+    if frozen {
+      self.internal_touch_record(&account_id);
+      self.log_event(
+        "account_frozen",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    frozen
+  }
+
+  /// Restores the whitelist status of the frozen account ID.
+  pub fn unfreeze_account(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    let unfrozen = self.frozen.remove(&account_id);
+    if unfrozen {
+      self.internal_touch_record(&account_id);
+      self.log_event(
+        "account_unfrozen",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    unfrozen
+  }
+
+  /// Flags the whitelisted account ID for re-verification, keeping it whitelisted.
+  pub fn flag_for_reverification(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    self.internal_get_record(&account_id);
+    let flagged = self.needs_reverification.insert(&account_id);
+    if flagged {
+      self.internal_touch_record(&account_id);
+      self.log_event(
+        "reverification_flagged",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    flagged
+  }
+
+  /// Clears the re-verification flag of the account ID after a successful re-verification.
+  pub fn clear_reverification_flag(&mut self, account_id: AccountId) -> bool {
+    self.assert_not_read_only();
+    self.assert_called_by_service();
+    let cleared = self.needs_reverification.remove(&account_id);
+    if cleared {
+      self.internal_touch_record(&account_id);
+      self.log_event(
+        "reverification_cleared",
+        json!({
+          "account_id": account_id,
+          "service_account_id": env::predecessor_account_id(),
+        }),
+      );
+    }
+    cleared
+  }
+
+  /**
+    Internal
+  **/
+
+  /// An internal method for initializing the contract with the given storage prefix.
+  fn internal_new(admin_pk: PublicKey, prefix: &str) -> Self {
+    let storage_key = |suffix: &str| format!("{}{}", prefix, suffix).into_bytes();
+    let mut contract = Self {
+      admin_pk,
+      created_at: env::block_timestamp(),
+      notify_contract: None,
+      fallback_whitelist: None,
+      event_standard: EVENT_STANDARD.to_string(),
+      event_version: EVENT_VERSION.to_string(),
+      service_accounts: UnorderedSet::new(storage_key("s")),
+      service_labels: LookupMap::new(storage_key("l")),
+      approval_counts: LookupMap::new(storage_key("c")),
+      applicants: UnorderedMap::new(storage_key("a")),
+      pk_index: LookupMap::new(storage_key("k")),
+      whitelisted_pk_index: LookupMap::new(storage_key("b")),
+      whitelist: UnorderedMap::new(storage_key("w")),
+      frozen: LookupSet::new(storage_key("f")),
+      needs_reverification: LookupSet::new(storage_key("r")),
+      max_whitelist_size: None,
+      admin_renounced: false,
+      applicant_ttl_ns: DEFAULT_APPLICANT_TTL_NS,
+      kyc_validity_ns: 0,
+      registration_fee: 0,
+      collected_fees: 0,
+      pending_admin_action: None,
+      rejected_until: LookupMap::new(storage_key("j")),
+      reject_cooldown_ns: 0,
+      guardians: UnorderedSet::new(storage_key("g")),
+      guardian_threshold: 0,
+      confirmations: LookupMap::new(storage_key("h")),
+      registration_paused: false,
+      whitelisting_paused: false,
+      max_applicants: None,
+      applicant_queue: TreeMap::new(storage_key("q")),
+      next_queue_position: 0,
+      allow_implicit_accounts: true,
+      removed: LookupMap::new(storage_key("d")),
+      service_quotas: LookupMap::new(storage_key("o")),
+      service_expirations: LookupMap::new(storage_key("e")),
+      service_rate_limit: None,
+      service_rate_window_ns: DEFAULT_SERVICE_RATE_WINDOW_NS,
+      service_rate_windows: LookupMap::new(storage_key("t")),
+      suspended_services: LookupSet::new(storage_key("i")),
+      purposes: UnorderedSet::new(storage_key("p")),
+      purpose_whitelist: LookupMap::new(storage_key("u")),
+      event_seq: 0,
+      allowed_curves: vec![CurveType::ED25519 as u8, CurveType::SECP256K1 as u8],
+      require_admin_approval: false,
+      pending_whitelist: UnorderedMap::new(storage_key("v")),
+      max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+      total_approvals: 0,
+      read_only: false,
+      delegated_admin: None,
+    };
+    let admin_pk = String::from(&contract.admin_pk);
+    contract.log_event(
+      "contract_initialized",
+      json!({
+        "admin_pk": admin_pk,
+        "spec_version": SPEC_VERSION,
+      }),
+    );
+    contract
+  }
+
+  /// An internal method for storing the applicant registered by a service account without a deposit.
+  fn internal_insert_applicant_for(&mut self, account_id: &AccountId, pk: &PublicKey) {
+    self.rejected_until.remove(account_id);
+    let applicant = Applicant {
+      pks: vec![pk.clone()],
+      submitted_at: env::block_timestamp(),
+      storage_deposit: 0,
+      queue_position: self.internal_enqueue_applicant(account_id),
+      doc_hash: None,
+      referrer: None,
+      contact: None,
+    };
+    self.applicants.insert(account_id, &applicant);
+    self.pk_index.insert(pk, account_id);
+  }
+
+  /// An internal method for appending the applicant account ID to the applicant queue.
+  /// Returns the assigned queue position.
+  fn internal_enqueue_applicant(&mut self, applicant_account_id: &AccountId) -> u64 {
+    let queue_position = self.next_queue_position;
+    self.applicant_queue.insert(&queue_position, applicant_account_id);
+    self.next_queue_position += 1;
+    queue_position
+  }
+
+  /// An internal method for deleting the applicant with its public keys index and queue position.
+  fn internal_delete_applicant(&mut self, applicant_account_id: &AccountId) -> Option<Applicant> {
+    let applicant = self.applicants.remove(applicant_account_id)?;
+    self.applicant_queue.remove(&applicant.queue_position);
+    for pk in applicant.pks.iter() {
+      self.pk_index.remove(pk);
+    }
+    Some(applicant)
+  }
+
+  /// An internal method for deleting the applicant and refunding its held storage deposit.
+  fn internal_delete_applicant_with_refund(&mut self, applicant_account_id: &AccountId) -> Option<Applicant> {
+    let applicant = self.internal_delete_applicant(applicant_account_id)?;
+    Self::internal_refund_storage_deposit(applicant_account_id, applicant.storage_deposit);
+    Some(applicant)
+  }
+
+  /// An internal method for transferring the held storage deposit back to the applicant.
+  fn internal_refund_storage_deposit(applicant_account_id: &AccountId, storage_deposit: Balance) {
+    if storage_deposit > 0 {
+      Promise::new(applicant_account_id.clone()).transfer(storage_deposit);
+    }
+  }
+
+  /// An internal method for whitelisting the applicant on behalf of the calling service account.
+  fn internal_approve_applicant(
+    &mut self,
+    account_id: AccountId,
+    mut record: WhitelistRecord,
+  ) -> Result<bool, ContractError> {
+    if self.whitelisting_paused {
+      return Err(ContractError::WhitelistingPaused);
+    }
+    if account_id == env::predecessor_account_id() {
+      return Err(ContractError::SelfWhitelisting);
+    }
+    if self.get_service_remaining_quota(env::predecessor_account_id()) == Some(0) {
+      return Err(ContractError::ServiceQuotaExhausted);
+    }
+    if self.whitelist.get(&account_id).is_some() || self.pending_whitelist.get(&account_id).is_some() {
+      return Ok(false);
+    }
+    // The applicant may have already removed itself, e.g. in the same block, it is whitelisted anyway.
+    // The applicant is read once by the deletion, which the returned error reverts.
+    let (public_keys, referrer, storage_deposit) = match self.internal_delete_applicant(&account_id) {
+      Some(applicant) if applicant.pks.is_empty() => return Err(ContractError::ApplicantHasNoKey),
+      Some(applicant) => (applicant.pks, applicant.referrer, applicant.storage_deposit),
+      None => (Vec::new(), None, 0),
+    };
+    Self::internal_refund_storage_deposit(&account_id, storage_deposit);
+    let service_account_id = env::predecessor_account_id();
+    record.verified_by = Some(service_account_id.clone());
+    record.referrer = referrer;
+    record.pks = public_keys.clone();
+    if self.require_admin_approval {
+      self.assert_valid_account_id(&account_id);
+      self.pending_whitelist.insert(&account_id, &record);
+      self.internal_record_service_approval(&service_account_id);
+      self.log_event(
+        "account_pending_approval",
+        json!({
+          "account_id": account_id,
+          "service_account_id": service_account_id,
+          "public_keys": public_keys,
+        }),
+      );
+      return Ok(true);
+    }
+    self.internal_insert_account(&account_id, record);
+    self.internal_record_service_approval(&service_account_id);
+    self.internal_notify_whitelisted(&account_id);
+    self.log_event(
+      "account_whitelisted",
+      json!({
+        "account_id": account_id,
+        "source": "service",
+        "service_account_id": service_account_id,
+        "public_keys": public_keys,
+      }),
+    );
+    Ok(true)
+  }
+
+  /// An internal method for counting the approval of the service account, also within the current rate limit window.
+  fn internal_record_service_approval(&mut self, service_account_id: &AccountId) {
+    let (window_start, count) = self.internal_get_rate_window(service_account_id);
+    if let Some(service_rate_limit) = self.service_rate_limit {
+      if count >= service_rate_limit {
+        env::panic_str("Service rate limit exceeded");
+      }
+    }
+    self.service_rate_windows.insert(service_account_id, &(window_start, count + 1));
+    let approval_count = self.get_service_approval_count(service_account_id.clone());
+    self.approval_counts.insert(service_account_id, &(approval_count + 1));
+  }
+
+  /// An internal method for notifying the consumer contract about the whitelisted account.
+  /// Fire-and-forget, the whitelisting does not depend on the outcome of the call.
+  fn internal_notify_whitelisted(&self, account_id: &AccountId) {
+    if let Some(notify_contract) = self.notify_contract.clone() {
+      ext_whitelist_consumer::on_account_whitelisted(
+        account_id.clone(),
+        notify_contract,
+        0,
+        ON_ACCOUNT_WHITELISTED_GAS,
+      );
+    }
+  }
+
+  /// An internal method for getting the start and the approval count of the current rate limit window.
+  fn internal_get_rate_window(&self, service_account_id: &AccountId) -> (u64, u64) {
+    let now = env::block_timestamp();
+    match self.service_rate_windows.get(service_account_id) {
+      Some((window_start, count)) if now - window_start < self.service_rate_window_ns => (window_start, count),
+      _ => (now, 0),
+    }
+  }
+
+  /// An internal method for creating the whitelist record expiring after the current KYC validity.
+  fn internal_new_record(&self, note: Option<String>) -> WhitelistRecord {
+    let mut record = WhitelistRecord::new(note);
+    if self.kyc_validity_ns > 0 {
+      record.expires_at = Some(env::block_timestamp() + self.kyc_validity_ns);
+    }
+    record
+  }
+
+  /// An internal method for storing the whitelist record of the account.
+  /// An already whitelisted account keeps its record untouched, use `renew_account` to extend it.
+  /// The capacity is checked after the insert, which the panic reverts, so the record is written only once.
+  fn internal_add_account(&mut self, account_id: &AccountId, record: WhitelistRecord) -> bool {
+    if self.whitelist.get(account_id).is_some() {
+      return false;
+    }
+    self.internal_insert_account(account_id, record);
+    true
+  }
+
+  /// An internal method for storing the whitelist record of the account known not to be whitelisted.
+  /// Whitelisting supersedes a pending administrator approval of the account.
+  /// The capacity check compares the whitelist length after the insert instead of reading the entry first,
+  /// so a capped whitelist costs no more gas than an uncapped one, see `test_add_account_gas`.
+  fn internal_insert_account(&mut self, account_id: &AccountId, record: WhitelistRecord) {
+    self.assert_valid_account_id(account_id);
+    self.removed.remove(account_id);
+    self.pending_whitelist.remove(account_id);
+    for pk in record.pks.iter() {
+      self.whitelisted_pk_index.insert(pk, account_id);
+    }
+    self.whitelist.insert(account_id, &record);
+    if self.max_whitelist_size.map_or(false, |max_whitelist_size| self.whitelist.len() > max_whitelist_size) {
+      env::panic_str("Whitelist capacity reached");
+    }
+    self.total_approvals += 1;
+  }
+
+  /// An internal method for deleting the whitelist record of the account with its flags.
+  fn internal_remove_account(&mut self, account_id: &AccountId) -> Option<WhitelistRecord> {
+    self.frozen.remove(account_id);
+    self.needs_reverification.remove(account_id);
+    let record = self.whitelist.remove(account_id)?;
+    self.internal_remove_whitelisted_pks(account_id, &record, &[]);
+    Some(record)
+  }
+
+  /// An internal method for unindexing the public keys of the whitelist record, except the retained ones.
+  fn internal_remove_whitelisted_pks(
+    &mut self,
+    account_id: &AccountId,
+    record: &WhitelistRecord,
+    retained: &[PublicKey],
+  ) {
+    for pk in record.pks.iter().filter(|pk| !retained.contains(pk)) {
+      if self.whitelisted_pk_index.get(pk).as_ref() == Some(account_id) {
+        self.whitelisted_pk_index.remove(pk);
+      }
+    }
+  }
+
+  /// An internal method for checking that the account is whitelisted, within its validity window and not frozen.
+  /// Every public membership query must delegate to it.
+  fn internal_is_active(&self, account_id: &AccountId) -> bool {
+    self.is_whitelisted_at(account_id.clone(), env::block_timestamp()) && !self.frozen.contains(account_id)
+  }
+
+  /// An internal method for marking the whitelist record of the account as changed, if it exists.
+  fn internal_touch_record(&mut self, account_id: &AccountId) {
+    if let Some(mut record) = self.whitelist.get(account_id) {
+      record.updated_at = env::block_timestamp();
+      self.whitelist.insert(account_id, &record);
+    }
+  }
+
+  /// An internal method for getting the removed account that is still within the retention window.
+  fn internal_get_restorable(&self, account_id: &AccountId) -> Option<RemovedAccount> {
+    self
+      .removed
+      .get(account_id)
+      .filter(|removed_account| env::block_timestamp() - removed_account.removed_at <= REMOVED_RETENTION_NS)
+  }
+
+  /// An internal method for getting the whitelist record of the account.
+  fn internal_get_record(&self, account_id: &AccountId) -> WhitelistRecord {
+    self
+      .whitelist
+      .get(account_id)
+      .unwrap_or_else(|| env::panic_str("Account ID is not whitelisted"))
+  }
+
+  /// An internal method for replacing all service accounts with the given ones.
+  /// Services kept in the new list retain their label, quota, approvals, expiration and suspension.
+  /// Returns the new number of service accounts.
+  fn internal_replace_service_accounts(&mut self, new_services: Vec<AccountId>) -> u64 {
+    let old_services = self.service_accounts.to_vec();
+    for service_account_id in old_services.iter() {
+      if !new_services.contains(service_account_id) {
+        self.internal_remove_service_account(service_account_id);
+      }
+    }
+    for service_account_id in new_services.iter() {
+      if self.service_accounts.insert(service_account_id) {
+        self.service_labels.insert(service_account_id, &String::new());
+      }
+    }
+    self.log_event(
+      "service_accounts_replaced",
+      json!({
+        "old_service_account_ids": old_services,
+        "new_service_account_ids": new_services,
+        "admin_pk": env::signer_account_pk(),
+      }),
+    );
+    self.service_accounts.len()
+  }
+
+  /// An internal method for removing the service account ID with all of its per-service data.
+  fn internal_remove_service_account(&mut self, service_account_id: &AccountId) -> bool {
+    if !self.service_accounts.remove(service_account_id) {
+      return false;
+    }
+    self.service_labels.remove(service_account_id);
+    self.approval_counts.remove(service_account_id);
+    self.service_quotas.remove(service_account_id);
+    self.service_expirations.remove(service_account_id);
+    self.service_rate_windows.remove(service_account_id);
+    self.suspended_services.remove(service_account_id);
+    self.log_event(
+      "service_account_removed",
+      json!({
+        "service_account_id": service_account_id,
+        "admin_pk": env::signer_account_pk(),
+      }),
+    );
+    true
+  }
+
+  /// Internal method to log the event in the NEP-297 format.
+  /// Every event carries a monotonic sequence number in the `seq` field of its data.
+  fn log_event(&mut self, event: &str, mut data: Value) {
+    self.event_seq += 1;
+    data["seq"] = json!(self.event_seq);
+    let event = json!({
+      "standard": self.event_standard,
+      "version": self.event_version,
+      "event": event,
+      "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+  }
+
+  /// An internal method for computing the action hash of adding the service account ID.
+  fn add_service_account_hash(service_account_id: &AccountId) -> Vec<u8> {
+    env::sha256(format!("add_service_account:{}", service_account_id).as_bytes())
+  }
+
+  /// An internal method for computing the action hash of managing the guardians.
+  fn guardian_action_hash(action: &str, argument: &str) -> Vec<u8> {
+    env::sha256(format!("{}:{}", action, argument).as_bytes())
+  }
+
+  /// An internal method for computing the action hash of replacing all service accounts with the given ones.
+  fn replace_service_accounts_hash(new_services: &[AccountId]) -> Vec<u8> {
+    let new_services: Vec<&str> = new_services.iter().map(|service_account_id| service_account_id.as_str()).collect();
+    env::sha256(format!("replace_service_accounts:{}", new_services.join(",")).as_bytes())
+  }
+
+  /// An internal method for counting the confirmations of the action hash, ignoring removed guardians.
+  fn internal_count_confirmations(&self, action_hash: &[u8]) -> usize {
+    self.confirmations.get(&action_hash.to_vec()).map_or(0, |guardian_pks| {
+      guardian_pks.iter().filter(|pk| self.guardians.contains(pk)).count()
+    })
+  }
+
+  /// Internal method to verify the action hash has enough guardian confirmations and consume them.
+  fn assert_confirmed_by_guardians(&mut self, action_hash: Vec<u8>) {
+    if self.guardian_threshold == 0 {
+      return;
+    }
+    if self.internal_count_confirmations(&action_hash) < self.guardian_threshold as usize {
+      env::panic_str("Not enough guardian confirmations");
+    }
+    self.confirmations.remove(&action_hash);
+  }
+
+  /// Internal method to verify the confirmation matches the contract account ID.
+  fn assert_confirmation(confirm: &AccountId) {
+    if confirm != &env::current_account_id() {
+      env::panic_str("Confirmation must match the contract account ID");
+    }
+  }
+
+  /// Internal method to verify the account ID can be registered as an applicant with the public key.
+  fn check_can_register(&self, account_id: &AccountId, pk: &PublicKey) -> Result<(), ContractError> {
+    if self.registration_paused {
+      return Err(ContractError::RegistrationPaused);
+    }
+    if self.whitelist.get(account_id).is_some() {
+      return Err(ContractError::AlreadyWhitelisted);
+    }
+    if self.pending_whitelist.get(account_id).is_some() {
+      return Err(ContractError::PendingApproval);
+    }
+    if let Some(rejected_until) = self.rejected_until.get(account_id) {
+      if env::block_timestamp() < rejected_until {
+        return Err(ContractError::ReapplicationCooldown);
+      }
+    }
+    self.check_supported_curve(pk)?;
+    self.check_pk_not_registered(pk)
+  }
+
+  /// Internal method to verify the curve type of the public key is allowed.
+  fn check_supported_curve(&self, pk: &PublicKey) -> Result<(), ContractError> {
+    if !self.allowed_curves.contains(&pk.as_bytes()[0]) {
+      return Err(ContractError::UnsupportedKeyCurve);
+    }
+    Ok(())
+  }
+
+  /// Internal method to verify the account ID can be whitelisted under the account ID policy.
+  fn assert_valid_account_id(&self, account_id: &AccountId) {
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+      env::panic_str("Invalid account ID");
+    }
+    let is_implicit = account_id.as_str().len() == 64
+      && account_id.as_str().chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    if is_implicit && !self.allow_implicit_accounts {
+      env::panic_str("Implicit account IDs are not allowed");
+    }
+  }
+
+  /// Internal method to verify the whitelist purpose is registered.
+  fn assert_known_purpose(&self, purpose: &str) {
+    if purpose != DEFAULT_PURPOSE && !self.purposes.contains(&purpose.to_string()) {
+      env::panic_str("Unknown purpose");
+    }
+  }
+
+  /// Internal method to verify a new applicant fits into the applicant queue.
+  fn check_applicant_capacity(&self) -> Result<(), ContractError> {
+    if let Some(max_applicants) = self.max_applicants {
+      if self.applicants.len() >= max_applicants {
+        return Err(ContractError::ApplicantQueueFull);
+      }
+    }
+    Ok(())
+  }
+
+  /// Internal method to verify the public key is not registered by any applicant.
+  fn check_pk_not_registered(&self, pk: &PublicKey) -> Result<(), ContractError> {
+    if self.pk_index.get(pk).is_some() {
+      return Err(ContractError::PublicKeyAlreadyRegistered);
+    }
+    Ok(())
+  }
+
+  /// Internal method to verify the number of entries passed to a batch method.
+  fn assert_batch_size(&self, size: usize) {
+    if size as u64 > self.max_batch_size {
+      env::panic_str("Batch too large");
+    }
+  }
+
+  /// Internal method to verify the length of the internal note.
+  fn assert_valid_note(note: &str) {
+    if note.chars().count() > MAX_NOTE_LENGTH {
+      env::panic_str("Note is too long");
+    }
+  }
+
+  /// Internal method to verify the signer was the whitelist administrator account ID.
+  fn assert_called_by_admin(&self) {
+    if self.admin_renounced {
+      env::panic_str("Administration has been renounced");
+    }
+    let signer_pk = env::signer_account_pk();
+    let is_delegate = self
+      .delegated_admin
+      .as_ref()
+      .map_or(false, |(pk, until)| pk == &signer_pk && env::block_timestamp() < *until);
+    if signer_pk != self.admin_pk && !is_delegate {
+      env::panic_str("Can only be called by whitelist administrator");
+    }
+  }
+
+  /// Internal method to verify the contract is not in read-only mode.
+  fn assert_not_read_only(&self) {
+    if self.read_only {
+      env::panic_str("Contract is in read-only mode");
+    }
+  }
+
+  /// Internal method to verify the predecessor was the service account ID.
+  fn assert_called_by_service(&self) {
+    if !self.is_service_account_whitelisted(env::predecessor_account_id()) {
+      env::panic_str("Can only be called by whitelist service account");
+    };
+    if let Some(expires_at) = self.service_expirations.get(&env::predecessor_account_id()) {
+      if env::block_timestamp() >= expires_at {
+        env::panic_str("Service grant has expired");
+      }
+    }
+    if self.suspended_services.contains(&env::predecessor_account_id()) {
+      env::panic_str("Service account is suspended");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  mod test_utils;
+
+  use super::*;
+  use near_sdk::test_utils::{get_created_receipts, get_logs};
+  use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+  use test_utils::*;
+
+  fn execute_admin_action(contract: &mut SmartWhitelistContract, action: AdminAction) {
+    let mut context = admin_context();
+    testing_env!(context.clone());
+    contract.queue_admin_action(action, ADMIN_ACTION_DELAY_NS);
+
+    context.block_timestamp = ADMIN_ACTION_DELAY_NS;
+    testing_env!(context);
+    contract.execute_admin_action();
+  }
+
+  #[test]
+  fn test_whitelist_flow() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+
+    testing_env!(context.clone());
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+
+    // Adding service account
+    testing_env!(context.clone());
+    assert!(contract.add_service_account(service_account(), service_label(), 0));
+    testing_env!(context.clone());
+    assert!(contract.is_service_account_whitelisted(service_account()));
+
+    testing_env!(context.clone());
+    assert!(!contract.is_whitelisted(user_account()));
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+    testing_env!(context.clone());
+    let mut result = contract.get_applicant_pk(user_account());
+    assert_eq!(result.unwrap(), user_pk());
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    assert!(contract.add_account(user_account()).unwrap());
+
+    testing_env!(context.clone());
+    result = contract.get_applicant_pk(user_account());
+    assert!(result.is_none());
+
+    testing_env!(context.clone());
+    assert!(contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  fn test_remove_service_account() {
+    let context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+    assert!(contract.add_service_account(service_account(), service_label(), 0));
+    assert!(contract.is_service_account_whitelisted(service_account()));
+
+    execute_admin_action(
+      &mut contract,
+      AdminAction::RemoveServiceAccount {
+        service_account_id: service_account(),
+      },
+    );
+
+    testing_env!(context.clone());
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+  }
+
+  #[test]
+  fn test_remove_applicant_manually() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.add_service_account(service_account(), service_label(), 0));
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    context.account_balance = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+    let mut result = contract.get_applicant_pk(user_account());
+    assert_eq!(result.unwrap(), user_pk());
+
+    testing_env!(context.clone());
+    result = contract.remove_applicant();
+    assert_eq!(result.unwrap(), user_pk());
+
+    testing_env!(context.clone());
+    result = contract.get_applicant_pk(user_account());
+    assert!(result.is_none());
+  }
+
+  #[test]
+  fn test_remove_account_manually() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.add_service_account(service_account(), service_label(), 0));
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_whitelisted(user_account()));
+
+    testing_env!(context.clone());
+    assert!(contract.remove_account(user_account(), RemovalReason::UserRequest));
+    assert!(get_logs()[0].contains("\"event\":\"account_removed\""));
+    assert!(get_logs()[0].contains("\"reason\":\"UserRequest\""));
+
+    testing_env!(context.clone());
+    assert!(!contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Can only be called by whitelist administrator")]
+  fn test_not_enough_admin_permissions() {
+    let context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "Can only be called by whitelist service account")]
+  fn test_not_enough_service_permissions() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(context.clone());
+    contract.add_account(user_account()).unwrap();
+  }
+
+  #[test]
+  fn test_retry_registration_incomplete_applicant() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(context.clone());
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::PublicKeyAlreadyRegistered));
+  }
+
+  #[test]
+  fn test_registration_applicant_is_already_whitelisted() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    contract.add_account(user_account()).unwrap();
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::AlreadyWhitelisted));
+  }
+
+  #[test]
+  fn test_whitelist_unregistered_applicant() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  fn test_account_note() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    assert!(contract.add_account_with_note(user_account(), "manual review".to_string()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_account_note(user_account()).unwrap(), "manual review");
+
+    testing_env!(context.clone());
+    contract.set_account_note(user_account(), "edge case".to_string());
+    assert_eq!(contract.get_account_note(user_account()).unwrap(), "edge case");
+  }
+
+  #[test]
+  #[should_panic(expected = "Note is too long")]
+  fn test_account_note_is_too_long() {
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      admin_pk().into_bytes(),
+    );
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    context = get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    context = get_context(
+      service_account().to_string(),
+      service_account().to_string(),
+      vec![0, 1, 2],
+    );
+    testing_env!(context.clone());
+    contract.add_account_with_note(user_account(), "a".repeat(257));
+  }
+
+  #[test]
+  fn test_freeze_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+
+    testing_env!(service_context());
+    assert!(contract.freeze_account(user_account()));
+    assert!(contract.is_frozen(user_account()));
+    assert!(!contract.is_whitelisted(user_account()));
+    assert!(get_logs()[0].contains("\"event\":\"account_frozen\""));
+
+    testing_env!(service_context());
+    assert!(contract.unfreeze_account(user_account()));
+    assert!(!contract.is_frozen(user_account()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert!(get_logs()[0].contains("\"event\":\"account_unfrozen\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Account ID is not whitelisted")]
+  fn test_freeze_not_whitelisted_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.freeze_account(user_account());
+  }
+
+  #[test]
+  #[should_panic(expected = "Whitelist capacity reached")]
+  fn test_whitelist_capacity_reached() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_max_whitelist_size(Some(0));
+    assert_eq!(contract.get_max_whitelist_size(), Some(0));
+    assert_eq!(contract.get_remaining_capacity(), Some(0));
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+  }
+
+  #[test]
+  fn test_update_applicant_pk() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(user_context());
+    assert_eq!(contract.update_applicant_pk(admin_pk()).unwrap(), user_pk());
+    assert_eq!(contract.get_applicant_pk(user_account()).unwrap(), admin_pk());
+    assert!(get_logs()[0].contains("\"event\":\"applicant_pk_updated\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Unknown applicant")]
+  fn test_update_unknown_applicant_pk() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.update_applicant_pk(admin_pk());
+  }
+
+  #[test]
+  fn test_get_metadata() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let metadata = contract.get_metadata();
+    assert_eq!(metadata.spec_version, SPEC_VERSION);
+    assert_eq!(metadata.name, CONTRACT_NAME);
+    assert_eq!(metadata.service_accounts_count, 1);
+    assert_eq!(metadata.applicants_count, 1);
+    assert_eq!(metadata.whitelisted_count, 0);
+  }
+
+  #[test]
+  fn test_get_storage_report() {
+    testing_env!(admin_context());
+    let contract = SmartWhitelistContract::new(admin_pk());
+    let report = contract.get_storage_report();
+    assert_eq!(report.storage_usage, env::storage_usage());
+    assert_eq!(report.storage_balance.0, env::account_balance());
+  }
+
+  #[test]
+  fn test_get_stats() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(alice_account());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let stats = contract.get_stats();
+    assert_eq!(stats.whitelisted_count, 1);
+    assert_eq!(stats.applicant_count, 1);
+    assert_eq!(stats.service_account_count, 1);
+    assert_eq!(stats.storage_usage, env::storage_usage());
+  }
+
+  #[test]
+  #[should_panic(expected = "Administration has been renounced")]
+  fn test_renounce_admin() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    execute_admin_action(
+      &mut contract,
+      AdminAction::RenounceAdmin {
+        confirm: admin_account(),
+      },
+    );
+    assert!(get_logs()[2].contains("\"event\":\"admin_renounced\""));
+
+    testing_env!(admin_context());
+    contract.add_service_account(service_account(), service_label(), 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "Confirmation must match the contract account ID")]
+  fn test_renounce_admin_wrong_confirmation() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.queue_admin_action(
+      AdminAction::RenounceAdmin {
+        confirm: user_account(),
+      },
+      ADMIN_ACTION_DELAY_NS,
+    );
+  }
+
+  #[test]
+  fn test_get_whitelist_entry() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert!(contract.get_whitelist_entry(user_account()).is_none());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = service_context();
+    context.block_index = 7;
+    context.block_timestamp = 1_000;
+    testing_env!(context);
+    contract.add_account(user_account()).unwrap();
+    contract.freeze_account(user_account());
+
+    let entry = contract.get_whitelist_entry(user_account()).unwrap();
+    assert_eq!(entry.account_id, user_account());
+    assert_eq!(entry.verified_by, Some(service_account()));
+    assert_eq!(entry.referrer, None);
+    assert_eq!(entry.whitelisted_block, 7);
+    assert_eq!(entry.valid_from, 0);
+    assert_eq!(entry.expires_at, None);
+    assert_eq!(entry.first_seen, 1_000);
+    assert_eq!(entry.updated_at, 1_000);
+    assert!(entry.frozen);
+    assert!(!entry.is_active);
+  }
+
+  #[test]
+  fn test_applicants_count() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert_eq!(contract.get_applicants_count(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.get_applicants_count(), 1);
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert_eq!(contract.get_applicants_count(), 0);
+    assert_eq!(contract.get_service_approval_count(service_account()), 1);
+  }
+
+  #[test]
+  fn test_service_label() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.get_service_label(service_account()).is_none());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert_eq!(contract.get_service_label(service_account()).unwrap(), service_label());
+  }
+
+  #[test]
+  #[should_panic(expected = "Service label is too long")]
+  fn test_service_label_is_too_long() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), "a".repeat(65), 0);
+  }
+
+  #[test]
+  fn test_admin_seed_whitelist() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    let account_ids = vec![user_account(), service_account(), user_account()];
+    assert_eq!(contract.admin_seed_whitelist(account_ids, false), 2);
+    assert!(contract.is_whitelisted(user_account()));
+    assert!(contract.is_whitelisted(service_account()));
+    assert!(get_logs()[1].contains("\"event\":\"whitelist_seeded\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Batch too large")]
+  fn test_admin_seed_whitelist_batch_too_large() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_seed_whitelist(vec![user_account(); DEFAULT_MAX_BATCH_SIZE as usize + 1], false);
+  }
+
+  #[test]
+  fn test_remove_service_account_cleans_up_label() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert!(contract.get_service_label(service_account()).is_some());
+
+    execute_admin_action(
+      &mut contract,
+      AdminAction::RemoveServiceAccount {
+        service_account_id: service_account(),
+      },
+    );
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+    assert!(contract.get_service_label(service_account()).is_none());
+  }
+
+  #[test]
+  fn test_expire_stale_applicants() {
+    let mut context = admin_context();
+    testing_env!(context.clone());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_applicant_ttl(100);
+
+    context = user_context();
+    context.block_timestamp = 1_000;
+    testing_env!(context.clone());
+    contract.register_applicant(None, None).unwrap();
+
+    context = service_context();
+    context.block_timestamp = 1_100;
+    testing_env!(context.clone());
+    assert_eq!(contract.get_applicant_age(user_account()), Some(100));
+    assert_eq!(contract.expire_stale_applicants(vec![user_account(), admin_account()], false), 0);
+
+    context.block_timestamp = 1_101;
+    testing_env!(context.clone());
+    assert_eq!(contract.expire_stale_applicants(vec![user_account(), admin_account()], false), 1);
+    assert!(contract.get_applicant_pk(user_account()).is_none());
+    assert!(get_logs()[0].contains("\"event\":\"applicants_expired\""));
+  }
+
+  #[test]
+  fn test_registration_fee() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_registration_fee(U128(applicant_deposit() / 2));
+    assert_eq!(contract.get_registration_fee().0, applicant_deposit() / 2);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.collected_fees, applicant_deposit() / 2);
+  }
+
+  #[test]
+  fn test_registration_fee_not_covered() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_registration_fee(U128(applicant_deposit()));
+    contract.rejected_until.insert(&user_account(), &0);
+
+    testing_env!(user_context());
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::InsufficientDeposit));
+    assert!(contract.get_applicant_pk(user_account()).is_none());
+    assert!(contract.pk_index.get(&user_pk()).is_none());
+    assert_eq!(contract.next_queue_position, 0);
+    assert_eq!(contract.rejected_until.get(&user_account()), Some(0));
+    assert_eq!(contract.collected_fees, 0);
+
+    testing_env!(admin_context());
+    contract.set_registration_fee(U128(applicant_deposit() + 1));
+
+    testing_env!(user_context());
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::InsufficientDeposit));
+    assert_eq!(contract.next_queue_position, 0);
+    assert_eq!(contract.rejected_until.get(&user_account()), Some(0));
+  }
+
+  #[test]
+  fn test_withdraw_fees() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_registration_fee(U128(applicant_deposit() / 2));
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = admin_context();
+    context.account_balance = applicant_deposit();
+    testing_env!(context);
+    contract.withdraw_fees(U128(applicant_deposit() / 4), admin_account());
+    assert_eq!(contract.get_collected_fees().0, applicant_deposit() / 4);
+  }
+
+  #[test]
+  fn test_failed_fees_withdrawal_restores_fees() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.collected_fees = applicant_deposit();
+
+    let mut context = admin_context();
+    context.account_balance = applicant_deposit();
+    testing_env!(context);
+    contract.withdraw_fees(U128(applicant_deposit() / 4), admin_account());
+    assert_eq!(get_created_receipts().len(), 2);
+
+    testing_env!(
+      admin_context(),
+      VMConfig::test(),
+      RuntimeFeesConfig::test(),
+      Default::default(),
+      vec![PromiseResult::Failed]
+    );
+    assert!(!contract.on_fees_withdrawn(U128(applicant_deposit() / 4)));
+    assert_eq!(contract.get_collected_fees().0, applicant_deposit());
+    assert!(get_logs()[0].contains("\"event\":\"fees_withdrawal_failed\""));
+
+    testing_env!(
+      admin_context(),
+      VMConfig::test(),
+      RuntimeFeesConfig::test(),
+      Default::default(),
+      vec![PromiseResult::Successful(vec![])]
+    );
+    assert!(contract.on_fees_withdrawn(U128(applicant_deposit() / 4)));
+    assert_eq!(contract.get_collected_fees().0, applicant_deposit());
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough collected fees")]
+  fn test_withdraw_more_than_collected_fees() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.withdraw_fees(U128(1), admin_account());
+  }
+
+  #[test]
+  fn test_get_account_by_pk() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.get_account_by_pk(user_pk()).unwrap(), user_account());
+
+    testing_env!(user_context());
+    contract.remove_applicant();
+    assert!(contract.get_account_by_pk(user_pk()).is_none());
+  }
+
+  #[test]
+  fn test_register_applicant_with_registered_pk() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      user_pk().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context);
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::PublicKeyAlreadyRegistered));
+  }
+
+  #[test]
+  #[should_panic(expected = "Admin action is time-locked")]
+  fn test_execute_time_locked_admin_action() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.queue_admin_action(
+      AdminAction::RemoveServiceAccount {
+        service_account_id: service_account(),
+      },
+      ADMIN_ACTION_DELAY_NS,
+    );
+    assert_eq!(contract.get_pending_admin_action().unwrap().1, ADMIN_ACTION_DELAY_NS);
+
+    contract.execute_admin_action();
+  }
+
+  #[test]
+  #[should_panic(expected = "Admin action must be delayed by at least 24 hours")]
+  fn test_queue_admin_action_without_delay() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.queue_admin_action(
+      AdminAction::RemoveServiceAccount {
+        service_account_id: service_account(),
+      },
+      ADMIN_ACTION_DELAY_NS - 1,
+    );
+  }
+
+  #[test]
+  fn test_register_applicant_event() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+    let logs = get_logs();
+    assert_eq!(logs.len(), 2);
+    assert!(logs[1].contains("\"event\":\"applicant_registered\""));
+    assert!(logs[1].contains(&format!("\"account_id\":\"{}\"", user_account())));
+  }
+
+  #[test]
+  fn test_register_multiple_applicant_pks() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = user_context();
+    context.signer_account_pk = admin_pk().into_bytes();
+    testing_env!(context);
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.get_applicant_pks(user_account()), vec![user_pk(), admin_pk()]);
+    assert_eq!(contract.get_account_by_pk(admin_pk()).unwrap(), user_account());
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert!(contract.get_applicant_pks(user_account()).is_empty());
+    assert!(contract.get_account_by_pk(user_pk()).is_none());
+    assert!(contract.get_account_by_pk(admin_pk()).is_none());
+  }
+
+  #[test]
+  fn test_flag_for_reverification() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert!(contract.flag_for_reverification(user_account()));
+    assert!(contract.needs_reverification(user_account()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert!(get_logs()[0].contains("\"event\":\"reverification_flagged\""));
+
+    testing_env!(service_context());
+    assert!(contract.clear_reverification_flag(user_account()));
+    assert!(!contract.needs_reverification(user_account()));
+    assert!(get_logs()[0].contains("\"event\":\"reverification_cleared\""));
+  }
+
+  #[test]
+  fn test_get_service_accounts_detailed() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.add_service_account(admin_account(), String::new(), 0);
+
+    assert_eq!(
+      contract.get_service_accounts_detailed(0, 10),
+      vec![(service_account(), service_label()), (admin_account(), String::new())]
+    );
+    assert_eq!(
+      contract.get_service_accounts_detailed(1, 10),
+      vec![(admin_account(), String::new())]
+    );
+  }
+
+  #[test]
+  fn test_membership_queries_agree_on_frozen_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    contract.freeze_account(user_account());
+
+    assert!(!contract.is_whitelisted(user_account()));
+    assert!(!contract.get_whitelist_entry(user_account()).unwrap().is_active);
+  }
+
+  #[test]
+  fn test_register_applicant_for() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    assert_eq!(contract.get_applicant_pk(user_account()).unwrap(), user_pk());
+    assert!(get_logs()[0].contains(&format!("\"service_account_id\":\"{}\"", service_account())));
+  }
+
+  #[test]
+  #[should_panic(expected = "Applicant account ID is already exists")]
+  fn test_register_applicant_for_existing_applicant() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), admin_pk());
+  }
+
+  #[test]
+  fn test_admin_add_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.admin_add_account(user_account()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert!(get_logs()[1].contains("\"source\":\"admin\""));
+  }
+
+  #[test]
+  fn test_service_cannot_whitelist_itself() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(service_account(), user_pk());
+    assert_eq!(contract.add_account(service_account()), Err(ContractError::SelfWhitelisting));
+  }
+
+  #[test]
+  fn test_migrate_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    contract.set_kyc_validity(1_000);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account_with_note(user_account(), "manual review".to_string());
+    contract.freeze_account(user_account());
+    let expires_at = contract.get_whitelist_entry(user_account()).unwrap().expires_at;
+    assert_eq!(expires_at, Some(1_000));
+
+    let mut context = get_context(
+      alice_account().to_string(),
+      alice_account().to_string(),
+      guardian_pks()[1].clone().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    context.account_balance = applicant_deposit();
+    testing_env!(context);
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = service_context();
+    context.block_timestamp = 500;
+    testing_env!(context);
+    contract.migrate_account(user_account(), alice_account());
+
+    assert!(contract.get_whitelist_entry(user_account()).is_none());
+    assert!(contract.get_applicant_pk(alice_account()).is_none());
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id.to_string(), alice_account().to_string());
+    assert!(contract.is_frozen(alice_account()));
+    assert_eq!(contract.get_account_note(alice_account()).unwrap(), "manual review");
+    assert_eq!(contract.get_whitelist_entry(alice_account()).unwrap().expires_at, expires_at);
+  }
+
+  #[test]
+  #[should_panic(expected = "Account ID is pending admin approval")]
+  fn test_migrate_account_to_pending_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+    contract.set_require_admin_approval(true);
+
+    testing_env!(service_context());
+    contract.add_account(alice_account()).unwrap();
+    contract.migrate_account(user_account(), alice_account());
+  }
+
+  #[test]
+  #[should_panic(expected = "Implicit account IDs are not allowed")]
+  fn test_migrate_account_to_forbidden_implicit_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+    contract.set_allow_implicit_accounts(false);
+
+    testing_env!(service_context());
+    contract.migrate_account(user_account(), "a".repeat(64).parse().unwrap());
+  }
+
+  #[test]
+  #[should_panic(expected = "Account ID is not whitelisted")]
+  fn test_migrate_not_whitelisted_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.migrate_account(user_account(), admin_account());
+  }
+
+  #[test]
+  fn test_whitelist_hash_is_independent_of_insertion_order() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    let empty_hash = contract.get_whitelist_hash();
+    contract.admin_seed_whitelist(vec![user_account(), service_account()], false);
+    let hash = contract.get_whitelist_hash();
+    assert_ne!(hash.0, empty_hash.0);
+
+    contract.whitelist.clear();
+    assert_eq!(contract.get_whitelist_hash().0, empty_hash.0);
+    contract.admin_seed_whitelist(vec![service_account(), user_account()], false);
+    assert_eq!(contract.get_whitelist_hash().0, hash.0);
+  }
+
+  #[test]
+  fn test_new_with_prefixes() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_seed_whitelist(vec![user_account()], false);
+
+    let mut other_contract = SmartWhitelistContract::new_with_prefixes(admin_pk(), "other".to_string());
+    assert!(!other_contract.is_whitelisted(user_account()));
+    other_contract.admin_seed_whitelist(vec![service_account()], false);
+    assert!(!contract.is_whitelisted(service_account()));
+  }
+
+  #[test]
+  fn test_register_applicant_without_deposit() {
+    let mut context = user_context();
+    context.attached_deposit = 0;
+    testing_env!(context);
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.get_applicant_storage_cost().0 > 0);
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::DepositRequired));
+  }
+
+  #[test]
+  fn test_admin_remove_applicants() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(admin_context());
+    assert_eq!(contract.admin_remove_applicants(vec![user_account(), service_account()], false), 1);
+    assert!(contract.get_applicant_pk(user_account()).is_none());
+    assert_eq!(get_logs().len(), 1);
+    assert!(get_logs()[0].contains("\"event\":\"applicants_removed\""));
+  }
+
+  #[test]
+  fn test_rejection_cooldown() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_reject_cooldown(100);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.reject_applicant(user_account(), "fraud".to_string(), false);
+    assert!(contract.get_applicant_pk(user_account()).is_none());
+    assert!(get_logs()[0].contains("\"event\":\"applicant_rejected\""));
+
+    let mut context = user_context();
+    context.block_timestamp = 100;
+    testing_env!(context);
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.get_applicant_pk(user_account()).unwrap(), user_pk());
+  }
+
+  #[test]
+  fn test_reapply_in_rejection_cooldown() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_reject_cooldown(100);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.reject_applicant(user_account(), "fraud".to_string(), false);
+
+    let mut context = user_context();
+    context.block_timestamp = 99;
+    testing_env!(context);
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::ReapplicationCooldown));
+  }
+
+  #[test]
+  fn test_guardian_confirmations() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    let action_hash = contract.get_add_service_account_hash(service_account());
+
+    for guardian_pk in guardian_pks().into_iter().take(2) {
+      testing_env!(get_context(
+        user_account().to_string(),
+        user_account().to_string(),
+        guardian_pk.into_bytes(),
+      ));
+      contract.confirm_action(action_hash.clone());
+    }
+    assert_eq!(contract.get_confirmations(action_hash.clone()), 2);
+
+    testing_env!(admin_context());
+    assert!(contract.add_service_account(service_account(), service_label(), 0));
+    assert_eq!(contract.get_confirmations(action_hash), 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough guardian confirmations")]
+  fn test_not_enough_guardian_confirmations() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    let action_hash = contract.get_add_service_account_hash(service_account());
+
+    testing_env!(get_context(
+      user_account().to_string(),
+      user_account().to_string(),
+      guardian_pks()[0].clone().into_bytes(),
+    ));
+    contract.confirm_action(action_hash);
+
+    testing_env!(admin_context());
+    contract.add_service_account(service_account(), service_label(), 0);
+  }
+
+  #[test]
+  fn test_removals_work_while_registration_paused() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+
+    testing_env!(admin_context());
+    contract.set_registration_paused(true);
+    assert!(contract.is_registration_paused());
+    assert!(!contract.is_whitelisting_paused());
+
+    testing_env!(service_context());
+    assert!(contract.remove_account(user_account(), RemovalReason::UserRequest));
+  }
+
+  #[test]
+  fn test_register_applicant_while_paused() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_registration_paused(true);
+
+    testing_env!(user_context());
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::RegistrationPaused));
+  }
+
+  #[test]
+  fn test_add_account_while_paused() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(admin_context());
+    contract.set_whitelisting_paused(true);
+
+    testing_env!(service_context());
+    assert_eq!(contract.add_account(user_account()), Err(ContractError::WhitelistingPaused));
+  }
+
+  #[test]
+  #[should_panic(expected = "Applicant queue is full")]
+  fn test_applicant_queue_is_full() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_max_applicants(Some(1));
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.get_remaining_applicant_capacity(), Some(0));
+
+    testing_env!(service_context());
+    contract.register_applicant_for(admin_account(), admin_pk());
+  }
+
+  #[test]
+  fn test_get_verifier_of() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(admin_account());
+    assert!(contract.get_verifier_of(admin_account()).is_none());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert_eq!(contract.get_verifier_of(user_account()).unwrap(), service_account());
+  }
+
+  #[test]
+  fn test_get_next_applicants() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.register_applicant_for(admin_account(), admin_pk());
+    contract.register_applicant_for(alice_account(), guardian_pks()[1].clone());
+
+    contract.reject_applicant(user_account(), "fraud".to_string(), false);
+    assert_eq!(
+      contract.get_next_applicants(10),
+      vec![
+        (admin_account(), admin_pk()),
+        (alice_account(), guardian_pks()[1].clone()),
+      ]
+    );
+
+    contract.add_account(admin_account()).unwrap();
+    assert_eq!(
+      contract.get_next_applicants(10),
+      vec![(alice_account(), guardian_pks()[1].clone())]
+    );
+  }
+
+  #[test]
+  fn test_on_whitelist_checked() {
+    testing_env!(admin_context());
+    let contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.on_whitelist_checked(true));
+    assert!(!contract.on_whitelist_checked(false));
+    assert_eq!(get_logs().len(), 1);
+  }
+
+  #[test]
+  fn test_whitelist_named_account_when_implicit_forbidden() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_allow_implicit_accounts(false);
+    assert!(contract.admin_add_account(user_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Implicit account IDs are not allowed")]
+  fn test_whitelist_implicit_account_when_forbidden() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_allow_implicit_accounts(false);
+    contract.admin_add_account(AccountId::new_unchecked("a".repeat(64)));
+  }
+
+  #[test]
+  fn test_add_account_verified() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    contract.submit_document_hash(Base64VecU8(vec![1; 32]));
+
+    testing_env!(service_context());
+    assert!(contract.add_account_verified(user_account(), Base64VecU8(vec![1; 32])));
+    assert!(contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Document hash mismatch")]
+  fn test_add_account_verified_hash_mismatch() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    contract.submit_document_hash(Base64VecU8(vec![1; 32]));
+
+    testing_env!(service_context());
+    contract.add_account_verified(user_account(), Base64VecU8(vec![2; 32]));
+  }
+
+  #[test]
+  fn test_restore_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account_with_note(user_account(), "passport".to_string());
+    assert!(contract.remove_account(user_account(), RemovalReason::Other("mistake".to_string())));
+    assert!(contract.is_restorable(user_account()));
+
+    assert!(contract.restore_account(user_account()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert!(!contract.is_restorable(user_account()));
+    assert_eq!(contract.get_verifier_of(user_account()), Some(service_account()));
+    assert_eq!(contract.get_account_note(user_account()), Some("passport".to_string()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Account ID cannot be restored")]
+  fn test_restore_account_after_retention() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+
+    testing_env!(service_context());
+    contract.remove_account(user_account(), RemovalReason::Other("mistake".to_string()));
+
+    let mut context = service_context();
+    context.block_timestamp = REMOVED_RETENTION_NS + 1;
+    testing_env!(context);
+    assert_eq!(contract.purge_removed_accounts(vec![user_account()], true), 1);
+    contract.restore_account(user_account());
+  }
+
+  #[test]
+  fn test_whitelist_for_purpose() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert!(contract.create_purpose("lending".to_string()));
+    assert!(contract.create_purpose("trading".to_string()));
+    assert!(!contract.get_purposes().contains(&DEFAULT_PURPOSE.to_string()));
+
+    testing_env!(service_context());
+    assert_eq!(contract.add_account_for_purpose("lending".to_string(), user_account()), Ok(true));
+    assert_eq!(contract.add_account_for_purpose("lending".to_string(), user_account()), Ok(false));
+    assert!(contract.is_whitelisted_for_purpose("lending".to_string(), user_account()));
+    assert!(!contract.is_whitelisted_for_purpose("trading".to_string(), user_account()));
+    assert!(!contract.is_whitelisted(user_account()));
+
+    testing_env!(admin_context());
+    contract.admin_add_account(user_account());
+    assert!(contract.is_whitelisted_for_purpose(DEFAULT_PURPOSE.to_string(), user_account()));
+
+    testing_env!(service_context());
+    assert!(contract.remove_account(user_account(), RemovalReason::UserRequest));
+    assert!(!contract.is_whitelisted(user_account()));
+    assert!(contract.is_whitelisted_for_purpose("lending".to_string(), user_account()));
+
+    assert!(contract.remove_account_for_purpose("lending".to_string(), user_account(), RemovalReason::UserRequest));
+    assert!(!contract.is_whitelisted_for_purpose("lending".to_string(), user_account()));
+    assert!(!contract.remove_account_for_purpose("lending".to_string(), user_account(), RemovalReason::UserRequest));
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid purpose")]
+  fn test_create_default_purpose() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.create_purpose(DEFAULT_PURPOSE.to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "Unknown purpose")]
+  fn test_whitelist_for_unknown_purpose() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+
+    testing_env!(service_context());
+    contract.add_account_for_purpose("lending".to_string(), user_account()).unwrap();
+  }
+
+  #[test]
+  #[should_panic(expected = "Service grant has expired")]
+  fn test_temporary_service_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.add_temporary_service_account(service_account(), service_label(), 0, 100));
+    assert_eq!(contract.get_service_expiry(service_account()), Some(100));
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    assert!(contract.add_account(user_account()).unwrap());
+
+    let mut context = service_context();
+    context.block_timestamp = 100;
+    testing_env!(context);
+    contract.remove_account(user_account(), RemovalReason::UserRequest);
+  }
+
+  #[test]
+  fn test_service_rate_limit() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_service_rate_limit(Some(1), 100);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.register_applicant_for(alice_account(), admin_pk());
+    assert!(contract.add_account(user_account()).unwrap());
+
+    let mut context = service_context();
+    context.block_timestamp = 100;
+    testing_env!(context);
+    assert!(contract.add_account(alice_account()).unwrap());
+  }
+
+  #[test]
+  #[should_panic(expected = "Service rate limit exceeded")]
+  fn test_service_rate_limit_exceeded() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_service_rate_limit(Some(1), 100);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.register_applicant_for(alice_account(), admin_pk());
+    assert!(contract.add_account(user_account()).unwrap());
+
+    let mut context = service_context();
+    context.block_timestamp = 99;
+    testing_env!(context);
+    contract.add_account(alice_account()).unwrap();
+  }
+
+  #[test]
+  fn test_reconcile_applicants() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    contract.applicants.remove(&user_account());
+
+    testing_env!(admin_context());
+    assert_eq!(contract.reconcile_applicants(vec![user_account()]), 1);
+    assert_eq!(contract.reconcile_applicant_pks(vec![user_pk()]), 1);
+    assert!(contract.applicant_queue.iter().next().is_none());
+    assert!(contract.get_account_by_pk(user_pk()).is_none());
+    assert_eq!(contract.reconcile_applicants(vec![user_account()]), 0);
+
+    testing_env!(user_context());
+    assert!(contract.register_applicant(None, None).unwrap().is_none());
+  }
+
+  #[test]
+  fn test_referrer() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(alice_account());
+
+    testing_env!(user_context());
+    contract.register_applicant(Some(alice_account()), None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert_eq!(contract.get_referrer_of(user_account()), Some(alice_account()));
+  }
+
+  #[test]
+  fn test_referrer_is_not_whitelisted() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+
+    testing_env!(user_context());
+    assert_eq!(contract.register_applicant(Some(alice_account()), None), Err(ContractError::ReferrerNotWhitelisted));
+  }
+
+  #[test]
+  fn test_event_sequence_numbers() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_add_account(user_account());
+    contract.admin_add_account(alice_account());
+    let logs = get_logs();
+    assert!(logs[0].contains("\"seq\":1"));
+    assert!(logs[1].contains("\"seq\":2"));
+    assert!(logs[2].contains("\"seq\":3"));
+  }
+
+  #[test]
+  fn test_get_all_service_accounts() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.add_service_account(alice_account(), service_label(), 0);
+    assert_eq!(contract.get_all_service_accounts(), vec![service_account(), alice_account()]);
+  }
+
+  #[test]
+  #[should_panic(expected = "Too many service accounts, use get_service_accounts_detailed")]
+  fn test_get_all_service_accounts_over_threshold() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for i in 0..=MAX_ALL_SERVICE_ACCOUNTS {
+      contract.service_accounts.insert(&AccountId::new_unchecked(format!("service{}", i)));
+    }
+    contract.get_all_service_accounts();
+  }
+
+  #[test]
+  fn test_whoami() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert_eq!(contract.whoami(), AccountStatus::Unknown);
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.whoami(), AccountStatus::Applicant);
+  }
+
+  #[test]
+  #[should_panic(expected = "Can only be called by whitelist administrator")]
+  fn test_delegate_admin() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    let delegate_pk = guardian_pks()[1].clone();
+    contract.delegate_admin(delegate_pk.clone(), 100);
+    assert_eq!(contract.get_delegated_admin(), Some((delegate_pk.clone(), 100)));
+
+    let mut context = get_context(
+      admin_account().to_string(),
+      admin_account().to_string(),
+      delegate_pk.into_bytes(),
+    );
+    testing_env!(context.clone());
+    contract.set_registration_paused(true);
+    assert!(contract.is_registration_paused());
+
+    context.block_timestamp = 100;
+    testing_env!(context);
+    contract.set_registration_paused(false);
+  }
+
+  #[test]
+  fn test_service_quota() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 1);
+    assert_eq!(contract.get_service_remaining_quota(service_account()), Some(1));
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.register_applicant_for(alice_account(), admin_pk());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert_eq!(contract.get_service_remaining_quota(service_account()), Some(0));
+    assert_eq!(contract.add_account(alice_account()), Err(ContractError::ServiceQuotaExhausted));
+  }
+
+  #[test]
+  fn test_get_whitelisted_block() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = service_context();
+    context.block_index = 42;
+    testing_env!(context);
+    contract.add_account(user_account()).unwrap();
+    assert_eq!(contract.get_whitelisted_block(user_account()), Some(42));
+
+    let mut context = admin_context();
+    context.block_index = 43;
+    testing_env!(context);
+    contract.admin_seed_whitelist(vec![alice_account()], false);
+    assert_eq!(contract.get_whitelisted_block(alice_account()), Some(43));
+  }
+
+  #[test]
+  #[should_panic(expected = "Contract is in read-only mode")]
+  fn test_read_only_mode() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_add_account(user_account());
+    contract.set_read_only(true);
+    assert!(contract.is_read_only());
+    assert!(contract.is_whitelisted(user_account()));
+    contract.admin_add_account(alice_account());
+  }
+
+  #[test]
+  fn test_remove_applicant_refunds_deposit() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+
+    let mut context = user_context();
+    context.attached_deposit = 0;
+    context.account_balance = applicant_deposit() * 2;
+    testing_env!(context);
+    assert_eq!(contract.remove_applicant(), Some(user_pk()));
+    assert_eq!(env::account_balance(), applicant_deposit());
+  }
+
+  #[test]
+  fn test_allowed_curves() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_allowed_curves(vec![CurveType::ED25519 as u8]);
+    assert_eq!(contract.get_allowed_curves(), vec![0]);
+
+    testing_env!(user_context());
+    assert!(contract.register_applicant(None, None).unwrap().is_none());
+
+    let mut secp256k1_pk = vec![CurveType::SECP256K1 as u8];
+    secp256k1_pk.extend([7; 64]);
+    let mut context = get_context(
+      alice_account().to_string(),
+      alice_account().to_string(),
+      secp256k1_pk,
+    );
+    context.attached_deposit = applicant_deposit();
+    testing_env!(context);
+    assert_eq!(contract.register_applicant(None, None), Err(ContractError::UnsupportedKeyCurve));
+  }
+
+  #[test]
+  fn test_service_account_events() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert!(get_logs()[1].contains("\"event\":\"service_account_added\""));
+    assert!(get_logs()[1].contains("\"service_account_id\":\"service\""));
+
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert_eq!(get_logs().len(), 2);
+
+    execute_admin_action(
+      &mut contract,
+      AdminAction::RemoveServiceAccount {
+        service_account_id: service_account(),
+      },
+    );
+    assert!(get_logs()[2].contains("\"event\":\"service_account_removed\""));
+    assert!(get_logs()[2].contains("\"service_account_id\":\"service\""));
+  }
+
+  #[test]
+  fn test_kyc_validity() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_kyc_validity(100);
+    assert_eq!(contract.get_kyc_validity(), 100);
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_kyc_expiry(user_account()), Some(100));
+
+    let mut context = admin_context();
+    context.block_timestamp = 99;
+    testing_env!(context.clone());
+    assert!(contract.is_whitelisted(user_account()));
+
+    context.block_timestamp = 100;
+    testing_env!(context);
+    contract.set_kyc_validity(1000);
+    assert!(!contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_account_status(user_account()), AccountStatus::Expired);
+  }
+
+  #[test]
+  fn test_expired_account_is_not_whitelisted_anywhere() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_kyc_validity(100);
+    contract.admin_add_account(user_account());
+    contract.admin_add_account(alice_account());
+
+    let mut context = admin_context();
+    context.block_timestamp = 100;
+    testing_env!(context);
+    contract.set_kyc_validity(0);
+    contract.admin_add_account(admin_account());
+    assert!(contract.whitelist.get(&user_account()).is_some());
+    assert!(!contract.is_whitelisted(user_account()));
+    assert_eq!(
+      contract.get_account_statuses(vec![user_account(), alice_account(), admin_account()]),
+      vec![AccountStatus::Expired, AccountStatus::Expired, AccountStatus::Whitelisted]
+    );
+    assert!(matches!(contract.is_whitelisted_with_fallback(user_account()), PromiseOrValue::Value(false)));
+
+    // The expired account is checked against the fallback whitelist instead of being whitelisted locally.
+    contract.set_fallback_whitelist(Some(service_account()));
+    assert!(matches!(contract.is_whitelisted_with_fallback(user_account()), PromiseOrValue::Promise(_)));
+    assert_eq!(get_created_receipts()[0].receiver_id.to_string(), service_account().to_string());
+    assert!(matches!(contract.is_whitelisted_with_fallback(admin_account()), PromiseOrValue::Value(true)));
+  }
+
+  #[test]
+  fn test_get_account_statuses() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(alice_account());
+    contract.admin_add_account(admin_account());
+
+    testing_env!(service_context());
+    contract.freeze_account(admin_account());
+    contract.register_applicant_for(user_account(), user_pk());
+
+    assert_eq!(
+      contract.get_account_statuses(vec![user_account(), alice_account(), admin_account(), service_account()]),
+      vec![
+        AccountStatus::Applicant,
+        AccountStatus::Whitelisted,
+        AccountStatus::Frozen,
+        AccountStatus::Unknown,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_add_account_after_applicant_removed_itself() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    contract.remove_applicant();
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  fn test_add_service_account_with_metadata() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert!(contract.get_service_account(service_account()).is_none());
+    assert!(contract.add_service_account_with_metadata(service_account(), service_label(), 10, Some(100)));
+
+    let service = contract.get_service_account(service_account()).unwrap();
+    assert_eq!(service.account_id, service_account());
+    assert_eq!(service.label, service_label());
+    assert_eq!(service.quota, Some(10));
+    assert_eq!(service.approval_count, 0);
+    assert_eq!(service.expires_at, Some(100));
+  }
+
+  #[test]
+  fn test_add_account_window() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    assert!(contract.add_account_window(user_account(), 100, 200));
+    assert!(!contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_account_status(user_account()), AccountStatus::Scheduled);
+
+    let mut context = service_context();
+    context.block_timestamp = 100;
+    testing_env!(context.clone());
+    assert!(contract.is_whitelisted(user_account()));
+
+    context.block_timestamp = 200;
+    testing_env!(context.clone());
+    assert!(contract.is_whitelisted(user_account()));
+
+    context.block_timestamp = 201;
+    testing_env!(context);
+    assert!(!contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid validity window")]
+  fn test_add_account_invalid_window() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.add_account_window(user_account(), 200, 100);
+  }
+
+  #[test]
+  fn test_is_whitelisted_at() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.add_account_window(user_account(), 100, 200);
+
+    let mut context = service_context();
+    context.block_timestamp = 150;
+    testing_env!(context);
+    assert!(!contract.is_whitelisted_at(user_account(), 99));
+    assert!(contract.is_whitelisted_at(user_account(), 100));
+    assert!(contract.is_whitelisted_at(user_account(), 200));
+    assert!(!contract.is_whitelisted_at(user_account(), 201));
+    assert!(!contract.is_whitelisted_at(alice_account(), 150));
+  }
+
+  #[test]
+  fn test_remove_account_with_other_reason() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+
+    testing_env!(service_context());
+    assert!(contract.remove_account(user_account(), RemovalReason::Other("duplicate".to_string())));
+    assert!(get_logs()[0].contains("\"reason\":{\"Other\":\"duplicate\"}"));
+  }
+
+  #[test]
+  #[should_panic(expected = "Reason is too long")]
+  fn test_remove_account_with_too_long_reason() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+
+    testing_env!(service_context());
+    contract.remove_account(user_account(), RemovalReason::Other("a".repeat(MAX_REASON_LENGTH + 1)));
+  }
+
+  #[test]
+  fn test_get_remaining_capacity() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert_eq!(contract.get_remaining_capacity(), None);
+
+    contract.set_max_whitelist_size(Some(3));
+    assert_eq!(contract.get_remaining_capacity(), Some(3));
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_remaining_capacity(), Some(2));
+    contract.admin_add_account(alice_account());
+    assert_eq!(contract.get_remaining_capacity(), Some(1));
+
+    testing_env!(service_context());
+    contract.remove_account(alice_account(), RemovalReason::UserRequest);
+    assert_eq!(contract.get_remaining_capacity(), Some(2));
+  }
+
+  #[test]
+  fn test_replace_service_accounts() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    execute_admin_action(
+      &mut contract,
+      AdminAction::ReplaceServiceAccounts {
+        new_services: vec![alice_account(), user_account()],
+      },
+    );
+    assert_eq!(contract.service_accounts.len(), 2);
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+    assert!(contract.get_service_label(service_account()).is_none());
+    assert!(contract.is_service_account_whitelisted(alice_account()));
+    assert!(contract.is_service_account_whitelisted(user_account()));
+    assert!(get_logs().last().unwrap().contains("\"event\":\"service_accounts_replaced\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Can only be called by whitelist service account")]
+  fn test_replaced_service_account_loses_access() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    execute_admin_action(
+      &mut contract,
+      AdminAction::ReplaceServiceAccounts {
+        new_services: vec![alice_account()],
+      },
+    );
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+  }
+
+  #[test]
+  fn test_account_whitelisted_event_contains_public_key() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    let log = get_logs().last().unwrap().clone();
+    assert!(log.contains("\"event\":\"account_whitelisted\""));
+    assert!(log.contains(&format!("\"public_keys\":[\"{}\"]", String::from(&user_pk()))));
+  }
+
+  #[test]
+  fn test_require_admin_approval() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_require_admin_approval(true);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(!contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_pending_whitelist_count(), 1);
+    assert_eq!(contract.get_pending_whitelist(0, 10), vec![user_account()]);
+
+    testing_env!(admin_context());
+    assert!(contract.admin_approve_pending(user_account()));
+    assert!(contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_verifier_of(user_account()), Some(service_account()));
+    assert_eq!(contract.get_pending_whitelist_count(), 0);
+  }
+
+  #[test]
+  fn test_without_admin_approval() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_whitelisted(user_account()));
+    assert_eq!(contract.get_pending_whitelist_count(), 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "Batch too large")]
+  fn test_set_max_batch_size() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert_eq!(contract.get_max_batch_size(), DEFAULT_MAX_BATCH_SIZE);
+    contract.set_max_batch_size(2);
+    assert_eq!(contract.get_max_batch_size(), 2);
+    assert_eq!(contract.admin_seed_whitelist(vec![user_account(), alice_account()], false), 2);
+    contract.admin_seed_whitelist(vec![user_account(); 3], false);
+  }
+
+  #[test]
+  fn test_contract_initialized_event() {
+    testing_env!(admin_context());
+    SmartWhitelistContract::new(admin_pk());
+    let logs = get_logs();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].contains("\"event\":\"contract_initialized\""));
+    assert!(logs[0].contains(&format!("\"admin_pk\":\"{}\"", String::from(&admin_pk()))));
+    assert!(logs[0].contains(&format!("\"spec_version\":\"{}\"", SPEC_VERSION)));
+  }
+
+  #[test]
+  fn test_is_pk_whitelisted() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    assert!(!contract.is_pk_whitelisted(user_pk()));
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    assert!(contract.is_pk_whitelisted(user_pk()));
+
+    contract.remove_account(user_account(), RemovalReason::UserRequest);
+    assert!(!contract.is_pk_whitelisted(user_pk()));
+  }
+
+  #[test]
+  fn test_admin_clear_whitelist() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_seed_whitelist(vec![user_account(), alice_account()], false);
+
+    testing_env!(service_context());
+    contract.freeze_account(user_account());
+
+    testing_env!(admin_context());
+    assert_eq!(contract.admin_clear_whitelist(admin_account(), 1), 1);
+    assert_eq!(contract.get_stats().whitelisted_count, 1);
+    assert_eq!(contract.admin_clear_whitelist(admin_account(), 1), 0);
+    assert_eq!(contract.get_stats().whitelisted_count, 0);
+    assert!(!contract.is_whitelisted(alice_account()));
+    assert!(!contract.is_frozen(user_account()));
+    assert!(get_logs().last().unwrap().contains("\"event\":\"whitelist_cleared\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Confirmation must match the contract account ID")]
+  fn test_admin_clear_whitelist_wrong_confirmation() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_clear_whitelist(user_account(), 1);
+  }
+
+  #[test]
+  fn test_total_approvals() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert_eq!(contract.get_total_approvals(), 0);
+    contract.admin_seed_whitelist(vec![user_account(), alice_account()], false);
+    assert_eq!(contract.get_total_approvals(), 2);
+
+    testing_env!(service_context());
+    contract.remove_account(user_account(), RemovalReason::UserRequest);
+    assert_eq!(contract.get_total_approvals(), 2);
+
+    testing_env!(admin_context());
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_total_approvals(), 3);
+  }
+
+  #[test]
+  fn test_revoke_applicant_pk_blocks_approval() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    assert_eq!(contract.revoke_applicant_pk(), Some(user_pk()));
+    assert!(contract.get_applicant_pks(user_account()).is_empty());
+    assert_eq!(contract.get_account_by_pk(user_pk()), None);
+    assert!(contract.get_applicant_age(user_account()).is_some());
+
+    testing_env!(service_context());
+    assert_eq!(contract.add_account(user_account()), Err(ContractError::ApplicantHasNoKey));
+    assert!(!contract.is_whitelisted(user_account()));
+  }
+
+  #[test]
+  fn test_revoke_then_update_applicant_pk() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    contract.revoke_applicant_pk();
+    assert_eq!(contract.update_applicant_pk(admin_pk()), None);
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_pk_whitelisted(admin_pk()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Account ID is already whitelisted")]
+  fn test_admin_seed_whitelist_strict() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_add_account(alice_account());
+    contract.admin_seed_whitelist(vec![user_account(), alice_account(), service_account()], true);
+  }
+
+  #[test]
+  fn test_admin_seed_whitelist_skips_invalid_entry() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_add_account(alice_account());
+    assert_eq!(
+      contract.admin_seed_whitelist(vec![user_account(), alice_account(), service_account()], false),
+      2
+    );
+    assert!(contract.is_whitelisted(service_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Unknown applicant")]
+  fn test_admin_remove_applicants_strict() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(admin_context());
+    contract.admin_remove_applicants(vec![alice_account(), user_account()], true);
+  }
+
+  #[test]
+  #[should_panic(expected = "Applicant is not stale")]
+  fn test_expire_stale_applicants_strict() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    let mut context = service_context();
+    context.block_timestamp = 1;
+    testing_env!(context);
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.expire_stale_applicants(vec![user_account(), alice_account()], true);
+  }
+
+  #[test]
+  #[should_panic(expected = "Removed account cannot be purged")]
+  fn test_purge_removed_accounts_strict() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+
+    testing_env!(service_context());
+    contract.remove_account(user_account(), RemovalReason::Other("mistake".to_string()));
+    contract.purge_removed_accounts(vec![user_account()], true);
+  }
+
+  #[test]
+  #[should_panic(expected = "Legacy account ID cannot be migrated")]
+  fn test_migrate_legacy_accounts_strict() {
+    testing_env!(admin_context());
+    let mut legacy = LegacySmartWhitelistContract::new(admin_pk());
+    legacy.whitelist.insert(&alice_account());
+    env::state_write(&legacy);
+
+    let mut contract = SmartWhitelistContract::migrate(Vec::new());
+    contract.migrate_legacy_accounts(vec![alice_account(), user_account()], true);
+  }
+
+  #[test]
+  fn test_check_invariants() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_max_whitelist_size(Some(10));
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.register_applicant_for(alice_account(), guardian_pks()[1].clone());
+    contract.add_account(user_account()).unwrap();
+
+    assert_eq!(
+      contract.check_invariants(),
+      InvariantReport {
+        applicants_map_matches_order_vector: true,
+        applicant_pks_indexed: true,
+        no_account_both_whitelisted_and_applicant: true,
+        no_account_both_whitelisted_and_pending: true,
+        whitelist_within_capacity: true,
+      }
+    );
+  }
+
+  #[test]
+  fn test_applicant_contact() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, Some("@user".to_string())).unwrap();
+
+    testing_env!(service_context());
+    let applicant = contract.get_applicant(user_account()).unwrap();
+    assert_eq!(applicant.contact, Some("@user".to_string()));
+    assert_eq!(applicant.pks, vec![user_pk()]);
+    assert!(contract.get_applicant(alice_account()).is_none());
+
+    contract.reject_applicant(user_account(), "fraud".to_string(), false);
+    let log = get_logs().last().unwrap().clone();
+    assert!(log.contains("\"had_contact\":true"));
+    assert!(!log.contains("@user"));
+  }
+
+  #[test]
+  fn test_applicant_contact_too_long() {
+    testing_env!(user_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert_eq!(
+      contract.register_applicant(None, Some("a".repeat(MAX_CONTACT_LENGTH + 1))),
+      Err(ContractError::ContactTooLong)
+    );
+  }
+
+  #[test]
+  fn test_get_created_at() {
+    let mut context = admin_context();
+    context.block_timestamp = 1_000_000;
+    testing_env!(context);
+    let contract = SmartWhitelistContract::new(admin_pk());
+
+    let mut context = admin_context();
+    context.block_timestamp = 2_000_000;
+    testing_env!(context);
+    assert_eq!(contract.get_created_at(), 1_000_000);
+  }
+
+  #[test]
+  fn test_get_my_service_info() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 3);
+    contract.set_service_rate_limit(Some(2), DEFAULT_SERVICE_RATE_WINDOW_NS);
+    assert!(contract.get_my_service_info().is_none());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    let info = contract.get_my_service_info().unwrap();
+    assert_eq!(info.account_id, service_account());
+    assert_eq!(info.label, service_label());
+    assert_eq!(info.approval_count, 1);
+    assert_eq!(info.remaining_quota, Some(2));
+    assert_eq!(info.remaining_rate_limit, Some(1));
+    assert_eq!(info.expires_at, None);
+  }
+
+  #[test]
+  fn test_notify_contract() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_notify_contract(Some(alice_account()));
+    assert_eq!(contract.get_notify_contract(), Some(alice_account()));
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.is_whitelisted(user_account()));
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[0].receiver_id.to_string(), user_account().to_string());
+    assert_eq!(receipts[1].receiver_id.to_string(), alice_account().to_string());
+  }
+
+  #[test]
+  fn test_register_applicants_for() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(admin_account());
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    let entries = vec![
+      (alice_account(), guardian_pks()[1].clone()),
+      (user_account(), guardian_pks()[2].clone()),
+      (admin_account(), guardian_pks()[2].clone()),
+      (alice_account(), guardian_pks()[2].clone()),
+    ];
+    assert_eq!(contract.register_applicants_for(entries), 1);
+    assert_eq!(contract.get_applicant_pks(alice_account()), vec![guardian_pks()[1].clone()]);
+    assert_eq!(contract.get_applicant_pks(user_account()), vec![user_pk()]);
+    assert!(contract.get_applicant_pks(admin_account()).is_empty());
+    assert!(get_logs().last().unwrap().contains("\"event\":\"applicants_registered\""));
+  }
+
+  #[test]
+  fn test_is_whitelisted_with_fallback() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.admin_add_account(user_account());
+    assert!(matches!(contract.is_whitelisted_with_fallback(alice_account()), PromiseOrValue::Value(false)));
+
+    contract.set_fallback_whitelist(Some(service_account()));
+    assert_eq!(contract.get_fallback_whitelist(), Some(service_account()));
+    assert!(matches!(contract.is_whitelisted_with_fallback(user_account()), PromiseOrValue::Value(true)));
+    assert!(get_created_receipts().is_empty());
+
+    assert!(matches!(contract.is_whitelisted_with_fallback(alice_account()), PromiseOrValue::Promise(_)));
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[0].receiver_id.to_string(), service_account().to_string());
+    assert_eq!(receipts[1].receiver_id.to_string(), admin_account().to_string());
+  }
+
+  #[test]
+  fn test_is_initialized() {
+    testing_env!(admin_context());
+    assert!(!SmartWhitelistContract::is_initialized());
+    let contract = SmartWhitelistContract::new(admin_pk());
+    env::state_write(&contract);
+    assert!(SmartWhitelistContract::is_initialized());
+  }
+
+  #[test]
+  fn test_set_event_standard() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    assert_eq!(contract.get_event_standard(), EVENT_STANDARD);
+    assert_eq!(contract.get_event_version(), EVENT_VERSION);
+    contract.set_event_standard("acme_whitelist".to_string(), "2.0.0".to_string());
+    assert_eq!(contract.get_event_standard(), "acme_whitelist");
+    assert_eq!(contract.get_event_version(), "2.0.0");
+    contract.admin_add_account(user_account());
+    let log = get_logs().last().unwrap().clone();
+    assert!(log.contains("\"standard\":\"acme_whitelist\""));
+    assert!(log.contains("\"version\":\"2.0.0\""));
+  }
+
+  #[test]
+  #[should_panic(expected = "Event standard and version must not be empty")]
+  fn test_set_event_standard_empty() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.set_event_standard(String::new(), "2.0.0".to_string());
+  }
+
+  #[test]
+  fn test_reject_applicant_with_refund() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    let collected_fees = contract.get_collected_fees();
+
+    let mut context = service_context();
+    context.account_balance = applicant_deposit();
+    testing_env!(context);
+    contract.reject_applicant(user_account(), "incomplete documents".to_string(), true);
+    assert_eq!(contract.get_collected_fees(), collected_fees);
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id.to_string(), user_account().to_string());
+    assert!(get_logs()[0].contains("\"refunded\":true"));
+  }
+
+  #[test]
+  fn test_reject_applicant_forfeits_deposit() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    let collected_fees = contract.get_collected_fees().0;
+
+    testing_env!(service_context());
+    contract.reject_applicant(user_account(), "fraud".to_string(), false);
+    assert!(contract.get_collected_fees().0 > collected_fees);
+    assert!(get_created_receipts().is_empty());
+    assert!(get_logs()[0].contains("\"refunded\":false"));
+  }
+
+  #[test]
+  fn test_first_seen_and_updated_at() {
+    let mut context = admin_context();
+    context.block_timestamp = 1_000;
+    testing_env!(context);
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_first_seen(user_account()), Some(1_000));
+    assert_eq!(contract.get_updated_at(user_account()), Some(1_000));
+
+    let mut context = service_context();
+    context.block_timestamp = 2_000;
+    testing_env!(context);
+    contract.renew_account(user_account());
+    assert_eq!(contract.get_first_seen(user_account()), Some(1_000));
+    assert_eq!(contract.get_updated_at(user_account()), Some(2_000));
+
+    let mut context = service_context();
+    context.block_timestamp = 3_000;
+    testing_env!(context);
+    contract.freeze_account(user_account());
+    assert_eq!(contract.get_updated_at(user_account()), Some(3_000));
+    contract.migrate_account(user_account(), alice_account());
+    assert_eq!(contract.get_first_seen(alice_account()), Some(1_000));
+    assert_eq!(contract.get_updated_at(alice_account()), Some(3_000));
+    assert_eq!(contract.get_first_seen(user_account()), None);
+  }
+
+  #[test]
+  fn test_get_directory() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.admin_seed_whitelist(vec![admin_account(), service_account()], false);
+
+    testing_env!(service_context());
+    contract.register_applicant_for(user_account(), user_pk());
+    contract.register_applicant_for(alice_account(), guardian_pks()[1].clone());
+
+    assert_eq!(
+      contract.get_directory(1, 2),
+      vec![
+        (alice_account(), AccountStatus::Applicant),
+        (admin_account(), AccountStatus::Whitelisted),
+      ]
+    );
+    assert_eq!(
+      contract.get_directory(3, 10),
+      vec![(service_account(), AccountStatus::Whitelisted)]
+    );
+    assert_eq!(contract.get_directory(0, 10).len(), 4);
+    assert!(contract.get_directory(4, 10).is_empty());
+  }
+
+  #[test]
+  fn test_admin_whitelisting_clears_applicant() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(admin_context());
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_applicant_pk(user_account()), None);
+    assert_eq!(contract.get_account_by_pk(user_pk()), None);
+  }
+
+  #[test]
+  fn test_admin_seed_whitelist_clears_applicant() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(admin_context());
+    assert_eq!(contract.admin_seed_whitelist(vec![user_account()], true), 1);
+    assert_eq!(contract.get_applicant_pk(user_account()), None);
+    assert_eq!(contract.get_account_by_pk(user_pk()), None);
+    assert!(contract.check_invariants().no_account_both_whitelisted_and_applicant);
   }
 
-  /**
-    Service
-  **/
+  #[test]
+  fn test_suspend_service_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    assert!(contract.suspend_service_account(service_account()));
+    assert!(contract.is_service_suspended(service_account()));
+    assert!(contract.get_service_account(service_account()).unwrap().suspended);
 
-  /// Adds a verified account ID to the whitelist.
-  pub fn add_account(&mut self, account_id: AccountId) -> bool {
-    self.assert_called_by_service();
-    self.internal_remove_applicant(account_id.clone());
-    self.whitelist.insert(&account_id)
-  }
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
 
-  /// Removes the given account ID from the whitelist.
-  pub fn remove_account(&mut self, account_id: AccountId) -> bool {
-    self.assert_called_by_service();
-    self.whitelist.remove(&account_id)
+    testing_env!(admin_context());
+    assert!(contract.resume_service_account(service_account()));
+    assert!(!contract.is_service_suspended(service_account()));
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
   }
 
-  /**
-    Internal
-  **/
+  #[test]
+  #[should_panic(expected = "Service account is suspended")]
+  fn test_suspended_service_cannot_approve() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.suspend_service_account(service_account());
 
-  /// An internal method for deleting the public key of the applicant's account.
-  fn internal_remove_applicant(&mut self, applicant_account_id: AccountId) -> Option<PublicKey> {
-    if !self.applicants.contains_key(&applicant_account_id) {
-      env::panic_str("Unknown applicant");
-    }
-    self.applicants.remove(&applicant_account_id)
-  }
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
 
-  /// Internal method to verify the signer was the whitelist administrator account ID.
-  fn assert_called_by_admin(&self) {
-    assert_eq!(
-      &env::signer_account_pk(),
-      &self.admin_pk,
-      "Can only be called by whitelist administrator"
-    );
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
   }
 
-  /// Internal method to verify the predecessor was the service account ID.
-  fn assert_called_by_service(&self) {
-    if !self.is_service_account_whitelisted(env::predecessor_account_id()) {
-      env::panic_str("Can only be called by whitelist service account");
-    };
+  #[test]
+  fn test_add_account_keeps_existing_record() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    contract.set_account_note(user_account(), "verified".to_string());
+    let whitelisted_block = contract.get_whitelisted_block(user_account());
+
+    assert!(!contract.add_account(user_account()).unwrap());
+    assert_eq!(contract.get_account_note(user_account()), Some("verified".to_string()));
+    assert_eq!(contract.get_verifier_of(user_account()), Some(service_account()));
+    assert_eq!(contract.get_whitelisted_block(user_account()), whitelisted_block);
+    assert!(contract.is_pk_whitelisted(user_pk()));
   }
-}
 
-#[cfg(test)]
-mod tests {
-  mod test_utils;
+  #[test]
+  fn test_renew_account() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_kyc_validity(1_000);
+    contract.admin_add_account(user_account());
+    assert_eq!(contract.get_kyc_expiry(user_account()), Some(1_000));
 
-  use super::*;
-  use near_sdk::testing_env;
-  use test_utils::*;
+    let mut context = service_context();
+    context.block_timestamp = 600;
+    testing_env!(context);
+    assert!(contract.flag_for_reverification(user_account()));
+    contract.renew_account(user_account());
+    assert_eq!(contract.get_kyc_expiry(user_account()), Some(1_600));
+    assert_eq!(contract.get_first_seen(user_account()), Some(0));
+    assert!(!contract.needs_reverification(user_account()));
+    assert!(get_logs()[1].contains("\"event\":\"account_renewed\""));
+    assert!(get_logs()[2].contains("\"event\":\"reverification_cleared\""));
+  }
 
   #[test]
-  fn test_whitelist_flow() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  fn test_admin_add_account_keeps_existing_record() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
 
-    testing_env!(context.clone());
-    assert!(!contract.is_service_account_whitelisted(service_account()));
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
 
-    // Adding service account
-    testing_env!(context.clone());
-    assert!(contract.add_service_account(service_account()));
-    testing_env!(context.clone());
-    assert!(contract.is_service_account_whitelisted(service_account()));
+    testing_env!(service_context());
+    contract.add_account(user_account()).unwrap();
+    contract.set_account_note(user_account(), "verified".to_string());
 
-    testing_env!(context.clone());
-    assert!(!contract.is_whitelisted(user_account()));
+    testing_env!(admin_context());
+    assert!(!contract.admin_add_account(user_account()));
+    assert!(get_logs().is_empty());
+    assert_eq!(contract.get_account_note(user_account()), Some("verified".to_string()));
+    assert_eq!(contract.get_verifier_of(user_account()), Some(service_account()));
+    assert!(contract.is_pk_whitelisted(user_pk()));
+  }
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    contract.register_applicant();
-    testing_env!(context.clone());
-    let mut result = contract.get_applicant_pk(user_account());
-    assert_eq!(result.unwrap(), user_pk());
+  #[test]
+  fn test_add_account_gas() {
+    let first_account = AccountId::new_unchecked("aaaa".to_string());
+    let second_account = AccountId::new_unchecked("bbbb".to_string());
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
 
-    context = get_context(
-      service_account().to_string(),
-      service_account().to_string(),
-      vec![0, 1, 2],
-    );
-    testing_env!(context.clone());
-    assert!(contract.add_account(user_account()));
+    let start_gas = env::used_gas().0;
+    contract.admin_add_account(first_account);
+    let uncapped_gas = env::used_gas().0 - start_gas;
 
-    testing_env!(context.clone());
-    result = contract.get_applicant_pk(user_account());
-    assert!(result.is_none());
+    contract.set_max_whitelist_size(Some(10));
+    let start_gas = env::used_gas().0;
+    contract.admin_add_account(second_account);
+    let capped_gas = env::used_gas().0 - start_gas;
 
-    testing_env!(context.clone());
-    assert!(contract.is_whitelisted(user_account()));
+    assert!(capped_gas <= uncapped_gas);
   }
 
   #[test]
-  fn test_remove_service_account() {
-    let context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    let mut contract = SmartWhitelistContract::new(admin_pk());
-    assert!(!contract.is_service_account_whitelisted(service_account()));
-    assert!(contract.add_service_account(service_account()));
+  fn test_migrate_legacy_state() {
+    let mut context = admin_context();
+    context.block_timestamp = 1_000_000;
+    testing_env!(context);
+    let mut legacy = LegacySmartWhitelistContract::new(admin_pk());
+    legacy.service_accounts.insert(&service_account());
+    legacy.whitelist.insert(&alice_account());
+    legacy.applicants.insert(&user_account(), &user_pk());
+    env::state_write(&legacy);
+
+    let mut contract = SmartWhitelistContract::migrate(vec![service_account()]);
+    assert_eq!(contract.get_created_at(), 1_000_000);
     assert!(contract.is_service_account_whitelisted(service_account()));
+    assert!(!contract.is_whitelisted(alice_account()));
 
-    testing_env!(context.clone());
-    assert!(contract.remove_service_account(service_account()));
+    assert_eq!(contract.migrate_legacy_accounts(vec![alice_account(), user_account(), service_account()], false), 2);
+    assert!(contract.is_whitelisted(alice_account()));
+    assert_eq!(contract.get_applicant_pk(user_account()), Some(user_pk()));
+    assert_eq!(contract.migrate_legacy_accounts(vec![alice_account(), user_account()], false), 0);
+    assert!(!legacy.whitelist.contains(&alice_account()));
+    assert!(!legacy.applicants.contains_key(&user_account()));
+  }
 
+  #[test]
+  fn test_migrate_keeps_created_at() {
+    let mut context = admin_context();
+    context.block_timestamp = 1_000_000;
     testing_env!(context.clone());
-    assert!(!contract.is_service_account_whitelisted(service_account()));
+    let contract = SmartWhitelistContract::new(admin_pk());
+    env::state_write(&contract);
+
+    context.block_timestamp = 2_000_000;
+    testing_env!(context);
+    let contract = SmartWhitelistContract::migrate(vec![]);
+    assert_eq!(contract.get_created_at(), 1_000_000);
   }
 
   #[test]
-  fn test_remove_applicant_manually() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  fn test_guardian_management_requires_confirmations() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    assert!(contract.add_service_account(service_account()));
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    let removed_pk = guardian_pks()[2].clone();
+    let remove_hash = contract.get_remove_guardian_hash(removed_pk.clone());
+    let threshold_hash = contract.get_set_guardian_threshold_hash(0);
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    contract.register_applicant();
-    let mut result = contract.get_applicant_pk(user_account());
-    assert_eq!(result.unwrap(), user_pk());
+    for guardian_pk in guardian_pks().into_iter().take(2) {
+      testing_env!(get_context(
+        user_account().to_string(),
+        user_account().to_string(),
+        guardian_pk.into_bytes(),
+      ));
+      contract.confirm_action(remove_hash.clone());
+      contract.confirm_action(threshold_hash.clone());
+    }
 
-    testing_env!(context.clone());
-    result = contract.remove_applicant();
-    assert_eq!(result.unwrap(), user_pk());
+    testing_env!(admin_context());
+    assert!(contract.remove_guardian(removed_pk));
+    contract.set_guardian_threshold(0);
+    assert_eq!(contract.guardian_threshold, 0);
+  }
 
-    testing_env!(context.clone());
-    result = contract.get_applicant_pk(user_account());
-    assert!(result.is_none());
+  #[test]
+  #[should_panic(expected = "Not enough guardian confirmations")]
+  fn test_lower_guardian_threshold_without_confirmations() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    contract.set_guardian_threshold(0);
   }
 
   #[test]
-  fn test_remove_account_manually() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  #[should_panic(expected = "Not enough guardian confirmations")]
+  fn test_remove_guardian_without_confirmations() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    assert!(contract.add_service_account(service_account()));
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    contract.remove_guardian(guardian_pks()[0].clone());
+  }
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
+  #[test]
+  #[should_panic(expected = "Admin action is time-locked")]
+  fn test_replace_service_accounts_is_time_locked() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.queue_admin_action(
+      AdminAction::ReplaceServiceAccounts {
+        new_services: vec![alice_account()],
+      },
+      ADMIN_ACTION_DELAY_NS,
     );
-    testing_env!(context.clone());
-    contract.register_applicant();
+    contract.execute_admin_action();
+  }
 
-    context = get_context(
-      service_account().to_string(),
-      service_account().to_string(),
-      vec![0, 1, 2],
-    );
-    testing_env!(context.clone());
-    assert!(contract.add_account(user_account()));
-    assert!(contract.is_whitelisted(user_account()));
+  #[test]
+  fn test_removed_applicants_deposit_refunded() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
 
-    testing_env!(context.clone());
-    assert!(contract.remove_account(user_account()));
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id.to_string(), user_account().to_string());
 
-    testing_env!(context.clone());
-    assert!(!contract.is_whitelisted(user_account()));
+    let mut context = get_context(
+      alice_account().to_string(),
+      alice_account().to_string(),
+      guardian_pks()[1].clone().into_bytes(),
+    );
+    context.attached_deposit = applicant_deposit();
+    context.account_balance = applicant_deposit();
+    testing_env!(context);
+    contract.register_applicant(None, None).unwrap();
+    testing_env!(admin_context());
+    assert_eq!(contract.admin_remove_applicants(vec![alice_account()], true), 1);
+    let receipts = get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id.to_string(), alice_account().to_string());
   }
 
   #[test]
-  #[should_panic(expected = "Can only be called by whitelist administrator")]
-  fn test_not_enough_admin_permissions() {
-    let context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  #[should_panic(expected = "Batch too large")]
+  fn test_admin_clear_whitelist_limit_too_large() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    contract.add_service_account(service_account());
+    contract.admin_clear_whitelist(admin_account(), DEFAULT_MAX_BATCH_SIZE + 1);
   }
 
   #[test]
-  #[should_panic(expected = "Can only be called by whitelist service account")]
-  fn test_not_enough_service_permissions() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  #[should_panic(expected = "Guardian threshold exceeds the number of guardians")]
+  fn test_guardian_threshold_above_guardian_count() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(4);
+  }
+
+  #[test]
+  #[should_panic(expected = "Not enough guardians left for the guardian threshold")]
+  fn test_remove_guardian_below_threshold() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    contract.add_service_account(service_account());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(3);
+    contract.remove_guardian(guardian_pks()[0].clone());
+  }
 
-    context = get_context(
+  #[test]
+  fn test_confirmations_of_removed_guardian_ignored() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    let action_hash = contract.get_add_service_account_hash(service_account());
+    testing_env!(get_context(
       user_account().to_string(),
       user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    contract.register_applicant();
+      guardian_pks()[0].clone().into_bytes(),
+    ));
+    contract.confirm_action(action_hash.clone());
+    assert_eq!(contract.get_confirmations(action_hash.clone()), 1);
 
-    testing_env!(context.clone());
-    contract.add_account(user_account());
+    testing_env!(admin_context());
+    contract.remove_guardian(guardian_pks()[0].clone());
+    assert_eq!(contract.get_confirmations(action_hash), 0);
   }
 
   #[test]
-  #[should_panic(expected = "Applicant account ID is already exists")]
-  fn test_retry_registration_incomplete_applicant() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  fn test_pending_account_keeps_its_record() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    contract.add_service_account(service_account());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.add_service_account(alice_account(), service_label(), 0);
+    contract.set_require_admin_approval(true);
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    contract.register_applicant();
+    testing_env!(user_context());
+    contract.register_applicant(None, None).unwrap();
 
-    testing_env!(context.clone());
-    contract.register_applicant();
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(!contract.add_account(user_account()).unwrap());
+    let mut context = service_context();
+    context.predecessor_account_id = alice_account();
+    context.signer_account_id = alice_account();
+    testing_env!(context);
+    assert!(!contract.add_account(user_account()).unwrap());
+    assert_eq!(contract.get_service_approval_count(alice_account()), 0);
+
+    testing_env!(admin_context());
+    assert!(contract.admin_approve_pending(user_account()));
+    assert_eq!(contract.get_verifier_of(user_account()), Some(service_account()));
+    assert!(contract.is_pk_whitelisted(user_pk()));
   }
 
   #[test]
-  #[should_panic(expected = "Account ID is already whitelisted")]
-  fn test_registration_applicant_is_already_whitelisted() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  fn test_admin_whitelisting_clears_pending_account() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    contract.add_service_account(service_account());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.set_require_admin_approval(true);
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
-    contract.register_applicant();
+    testing_env!(service_context());
+    assert!(contract.add_account(user_account()).unwrap());
+    assert!(contract.add_account(alice_account()).unwrap());
 
-    context = get_context(
-      service_account().to_string(),
-      service_account().to_string(),
-      vec![0, 1, 2],
-    );
-    testing_env!(context.clone());
-    contract.add_account(user_account());
+    testing_env!(admin_context());
+    assert!(contract.admin_add_account(user_account()));
+    assert_eq!(contract.admin_seed_whitelist(vec![alice_account()], true), 1);
+    assert_eq!(contract.get_pending_whitelist_count(), 0);
+    assert!(contract.check_invariants().no_account_both_whitelisted_and_pending);
+  }
 
-    context = get_context(
-      user_account().to_string(),
-      user_account().to_string(),
-      user_pk().into_bytes(),
+  #[test]
+  fn test_replace_service_accounts_keeps_retained_services() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_temporary_service_account(service_account(), service_label(), 3, 1_000);
+    contract.add_service_account(user_account(), service_label(), 0);
+    contract.suspend_service_account(service_account());
+    execute_admin_action(
+      &mut contract,
+      AdminAction::ReplaceServiceAccounts {
+        new_services: vec![service_account(), alice_account()],
+      },
     );
-    testing_env!(context.clone());
-    contract.register_applicant();
+
+    let service = contract.get_service_account(service_account()).unwrap();
+    assert_eq!(service.label, service_label());
+    assert_eq!(service.quota, Some(3));
+    assert_eq!(service.expires_at, Some(1_000));
+    assert!(service.suspended);
+    assert!(!contract.is_service_account_whitelisted(user_account()));
+    assert_eq!(contract.get_service_label(alice_account()), Some(String::new()));
   }
 
   #[test]
-  #[should_panic(expected = "Unknown applicant")]
-  fn test_attempt_to_whitelist_unregistered_applicant() {
-    let mut context = get_context(
-      admin_account().to_string(),
-      admin_account().to_string(),
-      admin_pk().into_bytes(),
-    );
-    testing_env!(context.clone());
+  fn test_replace_service_accounts_with_guardians() {
+    testing_env!(admin_context());
     let mut contract = SmartWhitelistContract::new(admin_pk());
-    contract.add_service_account(service_account());
+    contract.add_service_account(service_account(), service_label(), 0);
+    for guardian_pk in guardian_pks() {
+      contract.add_guardian(guardian_pk);
+    }
+    contract.set_guardian_threshold(2);
+    let new_services = vec![alice_account(), user_account()];
+    let action_hash = contract.get_replace_service_accounts_hash(new_services.clone());
+    for guardian_pk in guardian_pks().into_iter().take(2) {
+      testing_env!(get_context(
+        user_account().to_string(),
+        user_account().to_string(),
+        guardian_pk.into_bytes(),
+      ));
+      contract.confirm_action(action_hash.clone());
+    }
 
-    context = get_context(
-      service_account().to_string(),
-      service_account().to_string(),
-      vec![0, 1, 2],
+    testing_env!(admin_context());
+    assert_eq!(contract.replace_service_accounts(new_services), 2);
+    assert!(!contract.is_service_account_whitelisted(service_account()));
+    assert!(contract.is_service_account_whitelisted(alice_account()));
+  }
+
+  #[test]
+  #[should_panic(expected = "Service accounts can only be replaced through the timelock without guardians")]
+  fn test_replace_service_accounts_without_guardians() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.replace_service_accounts(vec![alice_account()]);
+  }
+
+  #[test]
+  fn test_purpose_whitelisting_counts_service_approvals() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 1);
+    contract.create_purpose("lending".to_string());
+    contract.set_require_admin_approval(true);
+
+    testing_env!(service_context());
+    assert_eq!(contract.add_account_for_purpose("lending".to_string(), user_account()), Ok(true));
+    assert!(contract.is_whitelisted_for_purpose("lending".to_string(), user_account()));
+    assert_eq!(contract.get_pending_whitelist_count(), 0);
+    assert_eq!(contract.get_service_approval_count(service_account()), 1);
+    assert_eq!(contract.get_total_approvals(), 1);
+    assert_eq!(
+      contract.add_account_for_purpose("lending".to_string(), alice_account()),
+      Err(ContractError::ServiceQuotaExhausted)
     );
-    testing_env!(context.clone());
-    contract.add_account(user_account());
+  }
+
+  #[test]
+  #[should_panic(expected = "Service rate limit exceeded")]
+  fn test_purpose_whitelisting_rate_limited() {
+    testing_env!(admin_context());
+    let mut contract = SmartWhitelistContract::new(admin_pk());
+    contract.add_service_account(service_account(), service_label(), 0);
+    contract.create_purpose("lending".to_string());
+    contract.set_service_rate_limit(Some(1), DEFAULT_SERVICE_RATE_WINDOW_NS);
+
+    testing_env!(service_context());
+    contract.add_account_for_purpose("lending".to_string(), user_account()).unwrap();
+    contract.add_account_for_purpose("lending".to_string(), alice_account()).unwrap();
   }
 }
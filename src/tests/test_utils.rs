@@ -1,4 +1,4 @@
-use near_sdk::{AccountId, PublicKey, VMContext};
+use near_sdk::{AccountId, Balance, PublicKey, VMContext};
 
 pub fn get_context(current_account: String, predecessor_account: String, signer_pk: Vec<u8>) -> VMContext {
   VMContext {
@@ -21,6 +21,37 @@ pub fn get_context(current_account: String, predecessor_account: String, signer_
   }
 }
 
+pub fn admin_context() -> VMContext {
+  get_context(
+    admin_account().to_string(),
+    admin_account().to_string(),
+    admin_pk().into_bytes(),
+  )
+}
+
+pub fn service_context() -> VMContext {
+  get_context(
+    service_account().to_string(),
+    service_account().to_string(),
+    vec![0, 1, 2],
+  )
+}
+
+pub fn user_context() -> VMContext {
+  let mut context = get_context(
+    user_account().to_string(),
+    user_account().to_string(),
+    user_pk().into_bytes(),
+  );
+  context.attached_deposit = applicant_deposit();
+  context.account_balance = applicant_deposit();
+  context
+}
+
+pub fn applicant_deposit() -> Balance {
+  10u128.pow(24)
+}
+
 pub fn admin_pk() -> PublicKey {
   "SaKC6KtLtuvUiSWL5jwurbmKXy1fQ8rgEjy9JisfTvQ".parse().unwrap()
 }
@@ -29,6 +60,14 @@ pub fn user_pk() -> PublicKey {
   "67eWTJ7sfpz27HdUYwmDGsWyvLSHfteF3sBdcqiMc1Mo".parse().unwrap()
 }
 
+pub fn guardian_pks() -> Vec<PublicKey> {
+  vec![
+    user_pk(),
+    "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx".parse().unwrap(),
+    "cGfHiC6Kgg3FpFZvgwGcswsCRtp4aBP2fzuXRQPizuN".parse().unwrap(),
+  ]
+}
+
 pub fn admin_account() -> AccountId {
   AccountId::new_unchecked("admin".to_string())
 }
@@ -37,6 +76,14 @@ pub fn service_account() -> AccountId {
   AccountId::new_unchecked("service".to_string())
 }
 
+pub fn alice_account() -> AccountId {
+  AccountId::new_unchecked("alice".to_string())
+}
+
 pub fn user_account() -> AccountId {
   AccountId::new_unchecked("user".to_string())
 }
+
+pub fn service_label() -> String {
+  "KYC backend".to_string()
+}